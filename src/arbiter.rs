@@ -0,0 +1,359 @@
+use crate::confidence::Confidence;
+use crate::dcf77::Error;
+use crate::sync::{Clock, RtcSync, SyncResult};
+use chrono::{Duration, NaiveDateTime};
+
+/// Time after which a sync may be replaced by one of lower quality
+const HOLD_MINUTES: i64 = 60;
+
+/// Origin of the time of the RTC, in increasing priority
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    /// free running, not synchronised since the start
+    Rtc,
+    /// set by hand
+    Manual,
+    /// DCF77 or one of the other radio time signals
+    Radio,
+    Gps,
+}
+
+/// Quality of a time, ranking the confidence before the source
+fn quality(source: Source, confidence: Confidence) -> (u8, Source) {
+    let confidence = match confidence {
+        Confidence::None => 0,
+        Confidence::Tentative(_) => 1,
+        Confidence::Confirmed => 2,
+    };
+    (confidence, source)
+}
+
+/// Selection of the time source allowed to synchronise the RTC
+///
+/// A source may always replace one of lower or equal quality. A recent sync
+/// is protected from sources of lower quality for an hour, after that any
+/// source is taken. Setting the time by hand always succeeds, it counts as
+/// confirmed but of the lowest priority.
+pub struct Arbiter {
+    source: Source,
+    confidence: Confidence,
+    last_sync: Option<NaiveDateTime>,
+}
+
+impl Default for Arbiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Arbiter {
+    pub fn new() -> Self {
+        Self {
+            source: Source::Rtc,
+            confidence: Confidence::None,
+            last_sync: None,
+        }
+    }
+
+    /// Source of the last sync
+    pub fn active(&self) -> Source {
+        self.source
+    }
+
+    /// Confidence of the active source at its last sync
+    pub fn confidence(&self) -> Confidence {
+        self.confidence
+    }
+
+    pub fn last_sync(&self) -> Option<NaiveDateTime> {
+        self.last_sync
+    }
+
+    /// Time since the last sync according to the RTC
+    pub fn age<R: Clock>(&self, rtc: &mut R) -> Result<Option<Duration>, R::Error> {
+        match self.last_sync {
+            Some(last_sync) => Ok(Some(rtc.datetime()? - last_sync)),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether `source` with `confidence` may synchronise the RTC
+    pub fn accepts<R: Clock>(
+        &self,
+        rtc: &mut R,
+        source: Source,
+        confidence: Confidence,
+    ) -> Result<bool, R::Error> {
+        if source == Source::Manual
+            || quality(source, confidence) >= quality(self.source, self.confidence)
+        {
+            return Ok(true);
+        }
+
+        Ok(match self.age(rtc)? {
+            Some(age) => age > Duration::minutes(HOLD_MINUTES),
+            None => true,
+        })
+    }
+
+    /// A second marker of `source`, with the time of the second if known
+    ///
+    /// The marker has been `delay_ms` ago, less than a second. If the source
+    /// is accepted the RTC is synchronised to the time, or without one at
+    /// least aligned to the second. Returns the result of the synchronisation.
+    pub fn second_marker<R: Clock>(
+        &mut self,
+        rtc: &mut R,
+        sync: &mut RtcSync,
+        source: Source,
+        confidence: Confidence,
+        time: nb::Result<NaiveDateTime, Error>,
+        delay_ms: i64,
+    ) -> Result<Option<SyncResult>, R::Error> {
+        if !self.accepts(rtc, source, confidence)? {
+            return Ok(None);
+        }
+
+        match time {
+            Ok(time) => {
+                let result = sync.second_marker(rtc, time, delay_ms)?;
+                self.synced(source, confidence, time);
+                Ok(Some(result))
+            }
+            Err(nb::Error::WouldBlock) => {
+                sync.phase_marker(rtc, delay_ms);
+                Ok(None)
+            }
+            Err(nb::Error::Other(_)) => Ok(None),
+        }
+    }
+
+    /// The time of `source`, which started about `delay_ms` ago
+    ///
+    /// For sources without a precise second marker, the RTC is only set when
+    /// it is off by a larger amount.
+    pub fn coarse_time<R: Clock>(
+        &mut self,
+        rtc: &mut R,
+        sync: &mut RtcSync,
+        source: Source,
+        confidence: Confidence,
+        time: NaiveDateTime,
+        delay_ms: i64,
+    ) -> Result<Option<SyncResult>, R::Error> {
+        if !self.accepts(rtc, source, confidence)? {
+            return Ok(None);
+        }
+
+        let result = sync.coarse_marker(rtc, time, delay_ms)?;
+        self.synced(source, confidence, time);
+        Ok(Some(result))
+    }
+
+    /// Set the RTC to `time` by hand, overriding any other source
+    pub fn set_manual<R: Clock>(
+        &mut self,
+        rtc: &mut R,
+        time: NaiveDateTime,
+    ) -> Result<(), R::Error> {
+        rtc.set_datetime(&time)?;
+        self.synced(Source::Manual, Confidence::Confirmed, time);
+        Ok(())
+    }
+
+    fn synced(&mut self, source: Source, confidence: Confidence, time: NaiveDateTime) {
+        self.source = source;
+        self.confidence = confidence;
+        self.last_sync = Some(time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::tests::Rtc;
+    use chrono::NaiveDate;
+
+    fn time(minute: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd(2021, 3, 1).and_hms(12, 0, 0) + Duration::minutes(minute)
+    }
+
+    #[test]
+    fn source_priority() {
+        let mut arbiter = Arbiter::new();
+        let mut sync = RtcSync::new();
+        let mut rtc = Rtc::new(time(0));
+        assert_eq!(arbiter.active(), Source::Rtc);
+
+        let result = arbiter.second_marker(
+            &mut rtc,
+            &mut sync,
+            Source::Gps,
+            Confidence::Confirmed,
+            Ok(time(1)),
+            0,
+        );
+        assert_eq!(result, Ok(Some(SyncResult::Updated { offset_ms: -60_000 })));
+        assert_eq!(
+            (arbiter.active(), arbiter.last_sync()),
+            (Source::Gps, Some(time(1)))
+        );
+
+        // the radio may neither set the RTC nor shift its phase
+        rtc.subsecond_ms = 300;
+        for &time in [Ok(time(2)), Err(nb::Error::WouldBlock)].iter() {
+            let result = arbiter.second_marker(
+                &mut rtc,
+                &mut sync,
+                Source::Radio,
+                Confidence::Confirmed,
+                time,
+                0,
+            );
+            assert_eq!(result, Ok(None));
+        }
+        assert_eq!((rtc.time, rtc.subsecond_ms, rtc.writes), (time(1), 300, 1));
+        assert_eq!(arbiter.active(), Source::Gps);
+
+        // but the source itself may
+        assert!(arbiter
+            .accepts(&mut rtc, Source::Gps, Confidence::Confirmed)
+            .unwrap());
+    }
+
+    #[test]
+    fn confidence() {
+        let mut arbiter = Arbiter::new();
+        let mut sync = RtcSync::new();
+        let mut rtc = Rtc::new(time(0));
+        let result = arbiter.coarse_time(
+            &mut rtc,
+            &mut sync,
+            Source::Gps,
+            Confidence::Tentative(1),
+            time(0),
+            0,
+        );
+        assert_eq!(result, Ok(Some(SyncResult::InSync { offset_ms: 0 })));
+        assert_eq!(arbiter.confidence(), Confidence::Tentative(1));
+
+        // a confirmed time of a lower priority source beats a tentative one
+        assert!(!arbiter
+            .accepts(&mut rtc, Source::Radio, Confidence::None)
+            .unwrap());
+        assert!(arbiter
+            .accepts(&mut rtc, Source::Radio, Confidence::Confirmed)
+            .unwrap());
+        arbiter
+            .second_marker(
+                &mut rtc,
+                &mut sync,
+                Source::Radio,
+                Confidence::Confirmed,
+                Ok(time(0)),
+                0,
+            )
+            .unwrap();
+        assert_eq!(arbiter.active(), Source::Radio);
+
+        assert!(!arbiter
+            .accepts(&mut rtc, Source::Gps, Confidence::Tentative(2))
+            .unwrap());
+        assert!(arbiter
+            .accepts(&mut rtc, Source::Gps, Confidence::Confirmed)
+            .unwrap());
+    }
+
+    #[test]
+    fn hold() {
+        let mut arbiter = Arbiter::new();
+        let mut sync = RtcSync::new();
+        let mut rtc = Rtc::new(time(0));
+        arbiter
+            .second_marker(
+                &mut rtc,
+                &mut sync,
+                Source::Gps,
+                Confidence::Confirmed,
+                Ok(time(0)),
+                0,
+            )
+            .unwrap();
+
+        rtc.time = time(HOLD_MINUTES);
+        assert_eq!(
+            arbiter.age(&mut rtc),
+            Ok(Some(Duration::minutes(HOLD_MINUTES)))
+        );
+        assert!(!arbiter
+            .accepts(&mut rtc, Source::Radio, Confidence::Tentative(1))
+            .unwrap());
+
+        // the GPS has been lost for more than an hour
+        rtc.time = time(HOLD_MINUTES + 1);
+        let result = arbiter.second_marker(
+            &mut rtc,
+            &mut sync,
+            Source::Radio,
+            Confidence::Tentative(1),
+            Ok(time(HOLD_MINUTES + 1)),
+            0,
+        );
+        assert_eq!(result, Ok(Some(SyncResult::InSync { offset_ms: 0 })));
+        assert_eq!(arbiter.active(), Source::Radio);
+    }
+
+    #[test]
+    fn manual() {
+        let mut arbiter = Arbiter::new();
+        let mut sync = RtcSync::new();
+        let mut rtc = Rtc::new(time(0));
+        arbiter
+            .second_marker(
+                &mut rtc,
+                &mut sync,
+                Source::Gps,
+                Confidence::Confirmed,
+                Ok(time(0)),
+                0,
+            )
+            .unwrap();
+
+        // setting the time by hand overrides even the GPS
+        assert_eq!(arbiter.set_manual(&mut rtc, time(-5)), Ok(()));
+        assert_eq!((rtc.time, rtc.writes), (time(-5), 1));
+        assert_eq!(
+            (arbiter.active(), arbiter.confidence()),
+            (Source::Manual, Confidence::Confirmed)
+        );
+
+        // it only holds against sources that are not confirmed
+        assert!(!arbiter
+            .accepts(&mut rtc, Source::Gps, Confidence::Tentative(1))
+            .unwrap());
+        assert!(arbiter
+            .accepts(&mut rtc, Source::Radio, Confidence::Confirmed)
+            .unwrap());
+
+        // and only for an hour
+        rtc.time = time(HOLD_MINUTES - 4);
+        assert!(arbiter
+            .accepts(&mut rtc, Source::Gps, Confidence::Tentative(1))
+            .unwrap());
+        let result = arbiter.coarse_time(
+            &mut rtc,
+            &mut sync,
+            Source::Gps,
+            Confidence::Tentative(1),
+            time(HOLD_MINUTES + 1),
+            0,
+        );
+        assert_eq!(
+            result,
+            Ok(Some(SyncResult::Updated {
+                offset_ms: -300_000
+            }))
+        );
+        assert_eq!(arbiter.active(), Source::Gps);
+    }
+}
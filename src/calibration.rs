@@ -0,0 +1,85 @@
+use chrono::NaiveDateTime;
+use stm32f0xx_hal::pac::{PWR, RTC};
+use word_clock::drift::{calibration_register, DriftEstimator};
+use word_clock::sync::SyncResult;
+
+/// Marks a valid estimate in the backup registers, combined with its value
+///
+/// The estimate is kept in BKP4R with the check word in BKP3R. The firmware
+/// uses no other backup register. `Rtc::open_or_init` is not relied upon to
+/// leave them alone: a value written there by anything else fails the check
+/// word, and the clock starts without a correction instead of a wrong one.
+const BACKUP_MAGIC: u32 = 0xCA1B_0001;
+
+/// Drift compensation of the RTC using the smooth calibration
+///
+/// The estimate is stored in the RTC backup registers, so it survives a
+/// reset as long as the backup domain is powered.
+pub struct Calibration {
+    estimator: DriftEstimator,
+}
+
+impl Calibration {
+    /// Restore a stored estimate and apply it
+    pub fn init() -> Self {
+        let correction_ppb = restore();
+        if let Some(correction_ppb) = correction_ppb {
+            apply(correction_ppb);
+        }
+
+        Self {
+            estimator: DriftEstimator::new(correction_ppb),
+        }
+    }
+
+    pub fn correction_ppb(&self) -> Option<i32> {
+        self.estimator.correction_ppb()
+    }
+
+    pub fn reset(&mut self) {
+        self.estimator.reset()
+    }
+
+    pub fn update(&mut self, time: NaiveDateTime, sync: SyncResult) {
+        if let Some(correction_ppb) = self.estimator.update(time, sync) {
+            apply(correction_ppb);
+            store(correction_ppb);
+        }
+    }
+}
+
+fn apply(correction_ppb: i32) {
+    let (calp, calm) = calibration_register(correction_ppb);
+    let rtc = unsafe { &*RTC::ptr() };
+
+    // disable write protection
+    rtc.wpr.write(|w| unsafe { w.key().bits(0xCA) });
+    rtc.wpr.write(|w| unsafe { w.key().bits(0x53) });
+    // a pending recalibration has to finish first
+    while rtc.isr.read().recalpf().bit_is_set() {}
+    rtc.calr
+        .write(|w| unsafe { w.calp().bit(calp).calm().bits(calm) });
+    rtc.wpr.write(|w| unsafe { w.key().bits(0xFF) });
+}
+
+fn store(correction_ppb: i32) {
+    let rtc = unsafe { &*RTC::ptr() };
+
+    // allow access to the backup domain
+    unsafe { (*PWR::ptr()).cr.modify(|_, w| w.dbp().set_bit()) };
+    let value = correction_ppb as u32;
+    rtc.bkp4r.write(|w| unsafe { w.bkp().bits(value) });
+    rtc.bkp3r
+        .write(|w| unsafe { w.bkp().bits(BACKUP_MAGIC ^ value) });
+}
+
+fn restore() -> Option<i32> {
+    let rtc = unsafe { &*RTC::ptr() };
+
+    let value = rtc.bkp4r.read().bkp().bits();
+    if rtc.bkp3r.read().bkp().bits() ^ value == BACKUP_MAGIC {
+        Some(value as i32)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,199 @@
+/// Nominal width of a pulse encoding a zero
+const ZERO_MS: u32 = 100;
+/// Nominal width of a pulse encoding a one
+const ONE_MS: u32 = 200;
+/// Nominal time from the start of second 58 to the minute marker
+const MINUTE_PERIOD_MS: u32 = 2000;
+/// How far a learned center may move away from the nominal width
+const MAX_DEVIATION_MS: u32 = 40;
+/// Fractional bits of the learned centers
+const FRACTION: u32 = 4;
+/// Inverse learning rate of the centers
+const LEARNING_RATE: i32 = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pulse {
+    Zero,
+    One,
+    /// too short to be a pulse, to be ignored
+    Glitch,
+    /// does not match any expected pulse width
+    Invalid,
+}
+
+/// Classification of the pulse widths of a DCF77 receiver
+///
+/// Depending on temperature and signal strength cheap receivers deliver
+/// pulses quite a bit off the nominal 100ms and 200ms. The classifier tracks
+/// the actual centers of both pulse widths over the recent history.
+#[derive(Copy, Clone, Debug)]
+pub struct PulseClassifier {
+    /// learned centers, fixed point with `FRACTION` bits
+    zero: u32,
+    one: u32,
+    tolerance_ms: u32,
+    glitch_ms: u32,
+}
+
+impl Default for PulseClassifier {
+    fn default() -> Self {
+        Self::new(50, 30)
+    }
+}
+
+impl PulseClassifier {
+    /// Pulses have to be within `tolerance_ms` of a learned center, pulses
+    /// shorter than `glitch_ms` are treated as glitches.
+    pub fn new(tolerance_ms: u32, glitch_ms: u32) -> Self {
+        Self {
+            zero: ZERO_MS << FRACTION,
+            one: ONE_MS << FRACTION,
+            tolerance_ms,
+            glitch_ms,
+        }
+    }
+
+    pub fn zero_ms(&self) -> u32 {
+        self.zero >> FRACTION
+    }
+
+    pub fn one_ms(&self) -> u32 {
+        self.one >> FRACTION
+    }
+
+    pub fn glitch_ms(&self) -> u32 {
+        self.glitch_ms
+    }
+
+    /// Classify a pulse of `width_ms` and learn from it
+    pub fn classify(&mut self, width_ms: u32) -> Pulse {
+        if width_ms < self.glitch_ms {
+            return Pulse::Glitch;
+        }
+
+        let (zero, one) = (self.zero_ms(), self.one_ms());
+        // decide for the nearest center first, so the windows never overlap
+        let (pulse, center, nominal) = if width_ms < (zero + one) / 2 {
+            (Pulse::Zero, &mut self.zero, ZERO_MS)
+        } else {
+            (Pulse::One, &mut self.one, ONE_MS)
+        };
+
+        let deviation = (width_ms as i32 - (*center >> FRACTION) as i32).unsigned_abs();
+        if deviation > self.tolerance_ms {
+            return Pulse::Invalid;
+        }
+
+        let target = (width_ms << FRACTION) as i32;
+        let learned = *center as i32 + (target - *center as i32) / LEARNING_RATE;
+        *center = (learned as u32).clamp(
+            (nominal - MAX_DEVIATION_MS) << FRACTION,
+            (nominal + MAX_DEVIATION_MS) << FRACTION,
+        );
+
+        pulse
+    }
+
+    /// Whether `gap_ms` after the end of a pulse is the minute marker
+    pub fn is_minute_gap(&self, gap_ms: u32) -> bool {
+        // a tolerance beyond the gap itself accepts anything down to zero
+        let min = (MINUTE_PERIOD_MS - self.one_ms()).saturating_sub(self.tolerance_ms);
+        let max = (MINUTE_PERIOD_MS - self.zero_ms()).saturating_add(self.tolerance_ms);
+        (min..=max).contains(&gap_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nominal() {
+        let mut classifier = PulseClassifier::default();
+        assert_eq!(classifier.classify(100), Pulse::Zero);
+        assert_eq!(classifier.classify(200), Pulse::One);
+        assert_eq!((classifier.zero_ms(), classifier.one_ms()), (100, 200));
+
+        // the nearest center wins
+        assert_eq!(PulseClassifier::default().classify(149), Pulse::Zero);
+        assert_eq!(PulseClassifier::default().classify(150), Pulse::One);
+    }
+
+    #[test]
+    fn drifting_centers() {
+        let mut classifier = PulseClassifier::default();
+        // the receiver stretches the pulses, e.g. while warming up
+        for stretch in 0..=30 {
+            for _ in 0..10 {
+                assert_eq!(classifier.classify(100 + stretch), Pulse::Zero);
+                assert_eq!(classifier.classify(200 + stretch), Pulse::One);
+            }
+        }
+        assert_eq!((classifier.zero_ms(), classifier.one_ms()), (129, 229));
+        // a pulse taken for a one at the nominal centers
+        assert_eq!(classifier.classify(175), Pulse::Zero);
+        // the minute marker moves along
+        assert!(classifier.is_minute_gap(1721));
+        assert!(!classifier.is_minute_gap(1930));
+    }
+
+    #[test]
+    fn centers_limited() {
+        let mut classifier = PulseClassifier::new(100, 30);
+        for _ in 0..100 {
+            assert_eq!(classifier.classify(148), Pulse::Zero);
+        }
+        assert_eq!(classifier.zero_ms(), ZERO_MS + MAX_DEVIATION_MS);
+        for _ in 0..100 {
+            assert_eq!(classifier.classify(50), Pulse::Zero);
+        }
+        assert_eq!(classifier.zero_ms(), ZERO_MS - MAX_DEVIATION_MS);
+    }
+
+    #[test]
+    fn out_of_tolerance() {
+        let mut classifier = PulseClassifier::new(20, 30);
+        assert_eq!(classifier.classify(115), Pulse::Zero);
+        assert_eq!(classifier.classify(90), Pulse::Zero);
+        let centers = (classifier.zero_ms(), classifier.one_ms());
+        assert_eq!(classifier.classify(125), Pulse::Invalid);
+        assert_eq!(classifier.classify(75), Pulse::Invalid);
+        assert_eq!(classifier.classify(175), Pulse::Invalid);
+        assert_eq!(classifier.classify(225), Pulse::Invalid);
+        assert_eq!(classifier.classify(500), Pulse::Invalid);
+        // invalid pulses are not learned from
+        assert_eq!((classifier.zero_ms(), classifier.one_ms()), centers);
+
+        let classifier = PulseClassifier::new(20, 30);
+        assert!(classifier.is_minute_gap(1780));
+        assert!(classifier.is_minute_gap(1920));
+        assert!(!classifier.is_minute_gap(1779));
+        assert!(!classifier.is_minute_gap(1921));
+    }
+
+    #[test]
+    fn large_tolerance() {
+        let classifier = PulseClassifier::new(3000, 30);
+        assert!(classifier.is_minute_gap(0));
+        assert!(classifier.is_minute_gap(4900));
+        assert!(!classifier.is_minute_gap(4901));
+
+        let classifier = PulseClassifier::new(u32::MAX, 30);
+        assert!(classifier.is_minute_gap(0));
+        assert!(classifier.is_minute_gap(u32::MAX));
+    }
+
+    #[test]
+    fn glitch() {
+        let mut classifier = PulseClassifier::default();
+        assert_eq!(classifier.classify(0), Pulse::Glitch);
+        assert_eq!(classifier.classify(29), Pulse::Glitch);
+        assert_eq!(classifier.classify(30), Pulse::Invalid);
+        assert_eq!((classifier.zero_ms(), classifier.one_ms()), (100, 200));
+
+        let mut classifier = PulseClassifier::new(50, 10);
+        assert_eq!(classifier.classify(9), Pulse::Glitch);
+        assert_eq!(classifier.classify(10), Pulse::Invalid);
+        assert_eq!(classifier.glitch_ms(), 10);
+    }
+}
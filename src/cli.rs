@@ -1,54 +1,140 @@
-use crate::cli::ParserResult::ParserError;
+use crate::SerialBuffer;
+use chrono::NaiveDateTime;
+use core::fmt::Write;
+use heapless::{consts::*, String};
 use menu::{Item, ItemType, Menu, Parameter};
-use stm32f0xx_hal::{
-    gpio::{gpiob, Alternate, AF0},
-    serial,
-};
+use word_clock::german::Variant;
+use word_clock::timezone::TimeZone;
 
-type Output = SerialOutput;
+/// Command waiting to be executed with the resources of the console task
+pub enum Request {
+    ReadTime,
+    SetTime(NaiveDateTime),
+    ReadVariant,
+    /// a variant, or `None` to follow the Schwaben-Schalter
+    SetVariant(Option<Variant>),
+    ReadTimeZone,
+    /// the time zone with the rules it has been given by
+    SetTimeZone(TimeZone, String<U48>),
+}
 
-const MENU: Menu<Output> = Menu {
+/// State of the console passed to the commands
+pub struct Output {
+    pub buffer: SerialBuffer,
+    pub request: Option<Request>,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self {
+            buffer: SerialBuffer::new(),
+            request: None,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.buffer.write_str(s)
+    }
+}
+
+pub static MENU: Menu<Output> = Menu {
     label: "root",
-    items: &[&Item {
-        command: "time",
-        help: Some("Retrieve the current internal time"),
-        item_type: ItemType::Callback {
-            function: command_time,
-            parameters: &[Parameter::Optional {
-                parameter_name: "new_time",
-                help: Some("if specified set the internal time to the given time"),
-            }],
+    items: &[
+        &Item {
+            command: "time",
+            help: Some("Retrieve the current internal time in UTC"),
+            item_type: ItemType::Callback {
+                function: command_time,
+                parameters: &[Parameter::Optional {
+                    parameter_name: "new_time",
+                    help: Some("if specified set the internal time, e.g. 2024-02-28T13:37:00"),
+                }],
+            },
+        },
+        &Item {
+            command: "variant",
+            help: Some("Retrieve the German phrasing"),
+            item_type: ItemType::Callback {
+                function: command_variant,
+                parameters: &[Parameter::Optional {
+                    parameter_name: "new_variant",
+                    help: Some("standard, half, swabian or switch to follow the switch"),
+                }],
+            },
+        },
+        &Item {
+            command: "timezone",
+            help: Some("Retrieve the rules of the local time zone"),
+            item_type: ItemType::Callback {
+                function: command_timezone,
+                parameters: &[Parameter::Optional {
+                    parameter_name: "new_rules",
+                    help: Some("POSIX TZ rules, e.g. CET-1CEST,M3.5.0,M10.5.0/3"),
+                }],
+            },
         },
-    }],
+    ],
 
     entry: None,
     exit: None,
 };
 
-struct SerialOutput {
-    tx: serial::Tx<gpiob::PB6<Alternate<AF0>>>,
-}
-
 fn command_time(_menu: &Menu<Output>, item: &Item<Output>, args: &[&str], context: &mut Output) {
-    if let Some(time) = ::menu::argument_finder(item, args, "new_time") {
-        // set new time
-    }
-
-    // print current time
+    context.request = match ::menu::argument_finder(item, args, "new_time") {
+        Ok(Some(time)) => match time.parse() {
+            Ok(time) => Some(Request::SetTime(time)),
+            Err(_) => {
+                writeln!(context, "invalid time {}", time).ok();
+                None
+            }
+        },
+        _ => Some(Request::ReadTime),
+    };
 }
 
-pub enum ParserResult {
-    ParserError,
-    NeedMoreData,
-    ReadTime,
-    SetTime,
+fn command_variant(_menu: &Menu<Output>, item: &Item<Output>, args: &[&str], context: &mut Output) {
+    context.request = match ::menu::argument_finder(item, args, "new_variant") {
+        Ok(Some("switch")) => Some(Request::SetVariant(None)),
+        Ok(Some(variant)) => match variant.parse() {
+            Ok(variant) => Some(Request::SetVariant(Some(variant))),
+            Err(()) => {
+                writeln!(context, "unknown variant {}", variant).ok();
+                None
+            }
+        },
+        _ => Some(Request::ReadVariant),
+    };
 }
 
-struct CLI {}
-
-impl CLI {
-    pub fn next_char(&mut self, c: u8) -> ParserResult {
-        /// add the next read character to the internal state
-        ParserError
-    }
+fn command_timezone(
+    _menu: &Menu<Output>,
+    item: &Item<Output>,
+    args: &[&str],
+    context: &mut Output,
+) {
+    context.request = match ::menu::argument_finder(item, args, "new_rules") {
+        Ok(Some(rules)) => {
+            let mut name = String::new();
+            match (TimeZone::parse(rules), name.push_str(rules)) {
+                (Ok(timezone), Ok(())) => Some(Request::SetTimeZone(timezone, name)),
+                (Err(error), _) => {
+                    writeln!(context, "invalid time zone {}, {:?}", rules, error).ok();
+                    None
+                }
+                (_, Err(())) => {
+                    writeln!(context, "time zone {} too long", rules).ok();
+                    None
+                }
+            }
+        }
+        _ => Some(Request::ReadTimeZone),
+    };
 }
@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use rtcc::Rtcc;
+use stm32f0xx_hal::{pac::RTC, rtc::Rtc};
+use word_clock::sync::Clock;
+
+/// The RTC as used by `word_clock::sync::RtcSync` and the arbiter
+impl Clock for Rtc {
+    type Error = <Rtc as Rtcc>::Error;
+
+    fn datetime(&mut self) -> Result<NaiveDateTime, Self::Error> {
+        Rtcc::get_datetime(self)
+    }
+
+    fn set_datetime(&mut self, time: &NaiveDateTime) -> Result<(), Self::Error> {
+        Rtcc::set_datetime(self, time)
+    }
+
+    fn subsecond_ms(&mut self) -> i64 {
+        let rtc = unsafe { &*RTC::ptr() };
+
+        // reading SSR locks the calendar shadow registers until DR is read
+        let ss = rtc.ssr.read().ss().bits() as i64;
+        let prediv_s = rtc.prer.read().prediv_s().bits() as i64;
+        (prediv_s - ss) * 1000 / (prediv_s + 1)
+    }
+
+    fn shift_ms(&mut self, offset_ms: i64) {
+        let rtc = unsafe { &*RTC::ptr() };
+        let prediv_s = rtc.prer.read().prediv_s().bits() as i64;
+
+        // the RTC can only be delayed by a fraction of a second, advancing
+        // it adds a full second first
+        let (add1s, delay_ms) = if offset_ms > 0 {
+            (true, 1000 - offset_ms)
+        } else {
+            (false, -offset_ms)
+        };
+        let subfs = (delay_ms * (prediv_s + 1) / 1000).min(prediv_s) as u16;
+
+        rtc.wpr.write(|w| unsafe { w.key().bits(0xCA) });
+        rtc.wpr.write(|w| unsafe { w.key().bits(0x53) });
+        // a pending shift has to finish first
+        while rtc.isr.read().shpf().bit_is_set() {}
+        rtc.shiftr
+            .write(|w| unsafe { w.add1s().bit(add1s).subfs().bits(subfs) });
+        rtc.wpr.write(|w| unsafe { w.key().bits(0xFF) });
+    }
+}
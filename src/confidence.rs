@@ -0,0 +1,159 @@
+use chrono::{Duration, NaiveDateTime};
+
+/// Level of trust into the time received from a time signal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Confidence {
+    /// no valid frame since the last error
+    None,
+    /// the given number of consecutive frames are consistent, but not yet
+    /// enough to trust them
+    Tentative(u8),
+    /// enough consecutive frames are exactly one minute apart
+    Confirmed,
+}
+
+/// Plausibility check over consecutive minute frames
+///
+/// A single frame passing the parity checks is still wrong every now and
+/// then. A time is only accepted after `required` consecutive frames which
+/// are each exactly one minute apart.
+pub struct Plausibility {
+    required: u8,
+    consecutive: u8,
+    last: Option<NaiveDateTime>,
+}
+
+impl Plausibility {
+    pub fn new(required: u8) -> Self {
+        Self {
+            required: required.max(1),
+            consecutive: 0,
+            last: None,
+        }
+    }
+
+    /// Add the time of the minute started with the latest minute marker
+    pub fn update<E>(&mut self, time: Result<NaiveDateTime, E>) {
+        match time {
+            Ok(time) => {
+                let expected = self.last.map(|last| last + Duration::minutes(1));
+                self.consecutive = if expected == Some(time) {
+                    self.consecutive.saturating_add(1).min(self.required)
+                } else {
+                    1
+                };
+                self.last = Some(time);
+            }
+            Err(_) => self.reset(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.consecutive = 0;
+        self.last = None;
+    }
+
+    pub fn confidence(&self) -> Confidence {
+        match self.consecutive {
+            0 => Confidence::None,
+            n if n >= self.required => Confidence::Confirmed,
+            n => Confidence::Tentative(n),
+        }
+    }
+
+    /// Start of the current minute, if it has been confirmed
+    pub fn confirmed(&self) -> Option<NaiveDateTime> {
+        match self.confidence() {
+            Confidence::Confirmed => self.last,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn minute(minute: u32) -> Result<NaiveDateTime, ()> {
+        Ok(NaiveDate::from_ymd(2024, 2, 28).and_hms(13, minute, 0))
+    }
+
+    #[test]
+    fn two_frames() {
+        let mut plausibility = Plausibility::new(2);
+        assert_eq!(plausibility.confidence(), Confidence::None);
+        plausibility.update(minute(37));
+        assert_eq!(plausibility.confidence(), Confidence::Tentative(1));
+        assert_eq!(plausibility.confirmed(), None);
+        plausibility.update(minute(38));
+        assert_eq!(plausibility.confidence(), Confidence::Confirmed);
+        assert_eq!(plausibility.confirmed(), minute(38).ok());
+        plausibility.update(minute(39));
+        assert_eq!(plausibility.confirmed(), minute(39).ok());
+    }
+
+    #[test]
+    fn three_frames() {
+        let mut plausibility = Plausibility::new(3);
+        plausibility.update(minute(37));
+        plausibility.update(minute(38));
+        assert_eq!(plausibility.confidence(), Confidence::Tentative(2));
+        assert_eq!(plausibility.confirmed(), None);
+        plausibility.update(minute(39));
+        assert_eq!(plausibility.confidence(), Confidence::Confirmed);
+        assert_eq!(plausibility.confirmed(), minute(39).ok());
+    }
+
+    #[test]
+    fn gap() {
+        let mut plausibility = Plausibility::new(2);
+        plausibility.update(minute(37));
+        // the frame of minute 38 got lost without an error
+        plausibility.update(minute(39));
+        assert_eq!(plausibility.confidence(), Confidence::Tentative(1));
+        plausibility.update(minute(40));
+        assert_eq!(plausibility.confidence(), Confidence::Confirmed);
+    }
+
+    #[test]
+    fn mismatch() {
+        let mut plausibility = Plausibility::new(3);
+        plausibility.update(minute(37));
+        plausibility.update(minute(38));
+        // a wrong frame passing the parity checks starts over
+        plausibility.update(minute(18));
+        assert_eq!(plausibility.confidence(), Confidence::Tentative(1));
+        // so does the correct one following it
+        plausibility.update(minute(40));
+        assert_eq!(plausibility.confidence(), Confidence::Tentative(1));
+        plausibility.update(minute(41));
+        plausibility.update(minute(42));
+        assert_eq!(plausibility.confirmed(), minute(42).ok());
+
+        // the same frame twice is no new minute either
+        plausibility.update(minute(42));
+        assert_eq!(plausibility.confidence(), Confidence::Tentative(1));
+    }
+
+    #[test]
+    fn error() {
+        let mut plausibility = Plausibility::new(2);
+        plausibility.update(minute(37));
+        plausibility.update(minute(38));
+        plausibility.update::<()>(Err(()));
+        assert_eq!(plausibility.confidence(), Confidence::None);
+        assert_eq!(plausibility.confirmed(), None);
+        // the error breaks the sequence
+        plausibility.update(minute(40));
+        assert_eq!(plausibility.confidence(), Confidence::Tentative(1));
+    }
+
+    #[test]
+    fn at_least_one_frame() {
+        let mut plausibility = Plausibility::new(0);
+        assert_eq!(plausibility.confidence(), Confidence::None);
+        plausibility.update(minute(37));
+        assert_eq!(plausibility.confidence(), Confidence::Confirmed);
+    }
+}
@@ -1,18 +1,18 @@
+use crate::classifier::{Pulse, PulseClassifier};
+use crate::confidence::{Confidence, Plausibility};
+use crate::filter::{EdgeFilter, FilterStats};
+use crate::phase::PhaseTracker;
+use crate::signal::TimeSignal;
+use crate::stats::ReceptionStats;
 use bit_field::BitField;
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Weekday};
 use core::convert::TryInto;
 use core::ops::RangeInclusive;
-use cortex_m_semihosting::hprintln;
-use embedded_hal::digital::v2::InputPin;
 use nb;
 use nb::Error::WouldBlock;
 use replace_with::replace_with;
-use stm32f0xx_hal::{
-    counter::Counter,
-    gpio::{Input, Pin, PullUp},
-};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Error {
     StartNotFound,
     StateChangeError,
@@ -22,103 +22,381 @@ pub enum Error {
     ParityErrorMinute,
     ParityErrorHour,
     ParityErrorDate,
+    /// Start of minute bit (0) is not zero
+    StartOfMinuteError,
+    /// Start of time bit (20) is not one
+    StartOfTimeError,
+    /// Neither or both of the CET/CEST bits (17, 18) are set
+    TimezoneError,
+    /// A BCD digit is larger than 9
+    InvalidBCD,
+    /// The minute is larger than 59
+    InvalidMinute,
+    /// The hour is larger than 23
+    InvalidHour,
+    /// The day of the month is not within 1 to 31
+    InvalidDay,
+    /// The month is not within 1 to 12
+    InvalidMonth,
+    /// The weekday is not within 1 to 7
+    InvalidWeekday,
+    /// The pulse of a bit could not be read
+    UnreadableBit,
+}
+
+/// Information decoded from a complete DCF77 minute frame.
+///
+/// The time information transmitted during a minute refers to the minute
+/// starting with the following minute marker.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DCF77Frame {
+    /// Call bit (15), signals irregularities of the transmitter
+    pub call_bit: bool,
+    /// A change between CET and CEST is announced for the end of the hour (16)
+    pub dst_announcement: bool,
+    /// Central european summer time is in effect (17)
+    pub cest: bool,
+    /// Central european time is in effect (18)
+    pub cet: bool,
+    /// A leap second is inserted at the end of the hour (19)
+    pub leap_second_announcement: bool,
+    pub minute: u32,
+    pub hour: u32,
+    pub day: u32,
+    pub weekday: Weekday,
+    pub month: u32,
+    /// full year, the century is inferred from the weekday
+    pub year: i32,
+}
+
+/// BCD number with the ones at `fst` followed by `tens` bits of the tens
+fn extract_number(bits: u64, fst: usize, tens: usize) -> u32 {
+    (bits.get_bits(fst..(fst + 4)) + bits.get_bits((fst + 4)..(fst + 4 + tens)) * 10)
+        .try_into()
+        .unwrap()
+}
+
+fn insert_number(bits: &mut u64, fst: usize, tens: usize, value: u32) {
+    bits.set_bits(fst..(fst + 4), (value % 10).into());
+    bits.set_bits((fst + 4)..(fst + 4 + tens), (value / 10).into());
+}
+
+/// First year a two digit year is taken to be in
+const FIRST_YEAR: i32 = 2000;
+/// Centuries from `FIRST_YEAR` on a two digit year may be in
+const CENTURIES: i32 = 2;
+
+/// Date with a two digit `year`, in the century where it falls on `weekday`
+///
+/// `date` builds the date for a full year. The weekday of a date moves by
+/// five or six days per century, so of the two centuries starting with
+/// `FIRST_YEAR` at most one matches. Searching further would move a date
+/// with a wrong weekday by centuries instead of rejecting it.
+pub fn infer_century(
+    year: u32,
+    weekday: Weekday,
+    date: impl Fn(i32) -> Option<NaiveDate>,
+) -> Result<NaiveDate, Error> {
+    let first = FIRST_YEAR + (year as i32 - FIRST_YEAR).rem_euclid(100);
+    (0..CENTURIES)
+        .filter_map(|century| date(first + 100 * century))
+        .find(|date| date.weekday() == weekday)
+        .ok_or(Error::InvalidDate)
+}
+
+/// Oldest confirmed frame to predict the current one from
+///
+/// The flags of the frame may have changed since then, which the repair
+/// counts as errors.
+const MAX_PREDICTION_MS: u32 = 24 * 60 * 60 * 1000;
+
+/// Bits of the frame which are protected by a parity and predictable
+const TIME_BITS: RangeInclusive<usize> = 20..=58;
+const PARITY_RANGES: [(RangeInclusive<usize>, Error); 3] = [
+    (21..=28, Error::ParityErrorMinute),
+    (29..=35, Error::ParityErrorHour),
+    (36..=58, Error::ParityErrorDate),
+];
+
+/// Check the numbers of the frame to be within their ranges
+///
+/// Valid BCD digits may still make up a minute of 79 or an hour of 39.
+fn check_ranges(bits: u64) -> Result<(), Error> {
+    if extract_number(bits, 21, 3) > 59 {
+        return Err(Error::InvalidMinute);
+    }
+    if extract_number(bits, 29, 2) > 23 {
+        return Err(Error::InvalidHour);
+    }
+    if !(1..=31).contains(&extract_number(bits, 36, 2)) {
+        return Err(Error::InvalidDay);
+    }
+    if !(1..=12).contains(&extract_number(bits, 45, 1)) {
+        return Err(Error::InvalidMonth);
+    }
+    if bits.get_bits(42..45) == 0 {
+        return Err(Error::InvalidWeekday);
+    }
+    Ok(())
+}
+
+impl DCF77Frame {
+    pub fn from_bits(bits: u64) -> Result<Self, Error> {
+        check_ranges(bits)?;
+        let weekday = match bits.get_bits(42..45) {
+            1 => Weekday::Mon,
+            2 => Weekday::Tue,
+            3 => Weekday::Wed,
+            4 => Weekday::Thu,
+            5 => Weekday::Fri,
+            6 => Weekday::Sat,
+            7 => Weekday::Sun,
+            _ => return Err(Error::InvalidWeekday),
+        };
+        let (day, month) = (extract_number(bits, 36, 2), extract_number(bits, 45, 1));
+        let date = infer_century(extract_number(bits, 50, 4), weekday, |year| {
+            NaiveDate::from_ymd_opt(year, month, day)
+        })?;
+
+        Ok(DCF77Frame {
+            call_bit: bits.get_bit(15),
+            dst_announcement: bits.get_bit(16),
+            cest: bits.get_bit(17),
+            cet: bits.get_bit(18),
+            leap_second_announcement: bits.get_bit(19),
+            minute: extract_number(bits, 21, 3),
+            hour: extract_number(bits, 29, 2),
+            day,
+            weekday,
+            month,
+            year: date.year(),
+        })
+    }
+
+    /// Encode the frame as transmitted, bits 1 to 14 are left zero
+    pub fn to_bits(&self) -> u64 {
+        let mut bits = 0u64;
+        bits.set_bit(15, self.call_bit);
+        bits.set_bit(16, self.dst_announcement);
+        bits.set_bit(17, self.cest);
+        bits.set_bit(18, self.cet);
+        bits.set_bit(19, self.leap_second_announcement);
+        bits.set_bit(20, true);
+        insert_number(&mut bits, 21, 3, self.minute);
+        insert_number(&mut bits, 29, 2, self.hour);
+        insert_number(&mut bits, 36, 2, self.day);
+        bits.set_bits(42..45, self.weekday.number_from_monday().into());
+        insert_number(&mut bits, 45, 1, self.month);
+        insert_number(&mut bits, 50, 4, (self.year % 100) as u32);
+
+        for (bit_range, _) in PARITY_RANGES.iter() {
+            let parity = bit_range
+                .clone()
+                .map(|bit| bits.get_bit(bit))
+                .fold(false, |acc, x| acc ^ x);
+            bits.set_bit(*bit_range.end(), parity);
+        }
+
+        bits
+    }
+
+    /// Frame expected to be transmitted during the following minute
+    ///
+    /// An announced change between CET and CEST takes place at the end of
+    /// the hour, the announcement ends with it. So does the announcement of
+    /// a leap second.
+    pub fn next_minute(&self) -> Result<Self, Error> {
+        let mut next = self.datetime(0)? + Duration::minutes(1);
+        let (mut cest, mut cet, mut dst_announcement) =
+            (self.cest, self.cet, self.dst_announcement);
+        if dst_announcement && next.minute() == 0 {
+            next += if cest {
+                Duration::hours(-1)
+            } else {
+                Duration::hours(1)
+            };
+            cest = !cest;
+            cet = !cet;
+            dst_announcement = false;
+        }
+        let weekday = if next.day() != self.day {
+            self.weekday.succ()
+        } else {
+            self.weekday
+        };
+
+        Ok(DCF77Frame {
+            dst_announcement,
+            leap_second_announcement: self.leap_second_announcement && next.minute() != 0,
+            cest,
+            cet,
+            minute: next.minute(),
+            hour: next.hour(),
+            day: next.day(),
+            weekday,
+            month: next.month(),
+            year: next.year(),
+            ..*self
+        })
+    }
+
+    pub fn date(&self) -> Result<NaiveDate, Error> {
+        NaiveDate::from_ymd_opt(self.year, self.month, self.day).ok_or(Error::InvalidDate)
+    }
+
+    pub fn datetime(&self, second: u32) -> Result<NaiveDateTime, Error> {
+        self.date()?
+            .and_hms_opt(self.hour, self.minute, second)
+            .ok_or(Error::InvalidTime)
+    }
+
+    /// Offset of the transmitted CET or CEST to UTC
+    pub fn utc_offset(&self) -> Result<Duration, Error> {
+        match (self.cest, self.cet) {
+            (true, false) => Ok(Duration::hours(2)),
+            (false, true) => Ok(Duration::hours(1)),
+            _ => Err(Error::TimezoneError),
+        }
+    }
+
+    /// Time of `second` of the frame in UTC
+    pub fn utc(&self, second: u32) -> Result<NaiveDateTime, Error> {
+        Ok(self.datetime(second)? - self.utc_offset()?)
+    }
 }
 
-struct DCF77Parser<Tim: Counter, S> {
+struct DCF77Parser<S> {
     current_bits: u64,
+    /// bits of the current frame which could not be read
+    current_unknown: u64,
+    current_len: usize,
     next_bits: u64,
-    timer: Tim,
+    next_unknown: u64,
     state: S,
 }
 
 struct Unknown {}
 struct AwaitingLow {
     bit: usize,
+    /// time since the end of the pulse already passed before the last edge
+    carry: u32,
 }
 struct AwaitingHigh {
     bit: usize,
+    /// time between the end of the previous pulse and this one
+    gap: u32,
 }
 
-impl<Tim: Counter, S> DCF77Parser<Tim, S> {
-    fn start_minute(self) -> DCF77Parser<Tim, AwaitingHigh> {
+impl<S> DCF77Parser<S> {
+    fn start_minute(self, len: usize, gap: u32) -> DCF77Parser<AwaitingHigh> {
         DCF77Parser {
             current_bits: self.next_bits,
+            current_unknown: self.next_unknown,
+            current_len: len,
             next_bits: 0,
-            timer: self.timer,
-            state: AwaitingHigh { bit: 0 },
+            next_unknown: 0,
+            state: AwaitingHigh { bit: 0, gap },
         }
     }
 }
 
-impl<Tim: Counter> DCF77Parser<Tim, Unknown> {
-    fn new(timer: Tim) -> Self {
+impl DCF77Parser<Unknown> {
+    fn new() -> Self {
         Self {
             current_bits: 0,
+            current_unknown: 0,
+            current_len: 0,
             next_bits: 0,
-            timer,
+            next_unknown: 0,
             state: Unknown {},
         }
     }
 }
 
-impl<Tim: Counter> DCF77Parser<Tim, AwaitingHigh> {
-    fn update(mut self, bit: bool) -> DCF77Parser<Tim, AwaitingLow> {
-        self.next_bits.set_bit(self.state.bit, bit);
+impl DCF77Parser<AwaitingHigh> {
+    fn update(mut self, bit: Option<bool>) -> DCF77Parser<AwaitingLow> {
+        self.next_bits.set_bit(self.state.bit, bit.unwrap_or(false));
+        self.next_unknown.set_bit(self.state.bit, bit.is_none());
         DCF77Parser {
             current_bits: self.current_bits,
+            current_unknown: self.current_unknown,
+            current_len: self.current_len,
             next_bits: self.next_bits,
-            timer: self.timer,
+            next_unknown: self.next_unknown,
             state: AwaitingLow {
                 bit: self.state.bit,
+                carry: 0,
             },
         }
     }
-}
 
-impl<Tim: Counter> From<DCF77Parser<Tim, AwaitingHigh>> for DCF77Parser<Tim, Unknown> {
-    fn from(old: DCF77Parser<Tim, AwaitingHigh>) -> Self {
+    /// Drop a glitch of `width` wrongly taken as start of the second
+    fn glitch(self, width: u32) -> DCF77Parser<AwaitingLow> {
         DCF77Parser {
-            current_bits: old.current_bits,
-            next_bits: 0,
-            timer: old.timer,
-            state: Unknown {},
+            current_bits: self.current_bits,
+            current_unknown: self.current_unknown,
+            current_len: self.current_len,
+            next_bits: self.next_bits,
+            next_unknown: self.next_unknown,
+            state: AwaitingLow {
+                bit: self.state.bit - 1,
+                carry: self.state.gap + width,
+            },
         }
     }
 }
 
-impl<Tim: Counter> From<DCF77Parser<Tim, AwaitingLow>> for DCF77Parser<Tim, AwaitingHigh> {
-    fn from(old: DCF77Parser<Tim, AwaitingLow>) -> Self {
+impl DCF77Parser<AwaitingLow> {
+    fn next_second(self, gap: u32) -> DCF77Parser<AwaitingHigh> {
         DCF77Parser {
-            current_bits: old.current_bits,
-            next_bits: old.next_bits,
-            timer: old.timer,
+            current_bits: self.current_bits,
+            current_unknown: self.current_unknown,
+            current_len: self.current_len,
+            next_bits: self.next_bits,
+            next_unknown: self.next_unknown,
             state: AwaitingHigh {
-                bit: old.state.bit + 1,
+                bit: self.state.bit + 1,
+                gap,
             },
         }
     }
 }
 
-impl<Tim: Counter> From<DCF77Parser<Tim, AwaitingLow>> for DCF77Parser<Tim, Unknown> {
-    fn from(old: DCF77Parser<Tim, AwaitingLow>) -> Self {
+impl From<DCF77Parser<AwaitingHigh>> for DCF77Parser<Unknown> {
+    fn from(old: DCF77Parser<AwaitingHigh>) -> Self {
         DCF77Parser {
             current_bits: old.current_bits,
+            current_unknown: old.current_unknown,
+            current_len: old.current_len,
             next_bits: 0,
-            timer: old.timer,
+            next_unknown: 0,
             state: Unknown {},
         }
     }
 }
 
-enum DCF77StateWrapper<Tim: Counter> {
-    Unknown(DCF77Parser<Tim, Unknown>),
-    AwaitingLow(DCF77Parser<Tim, AwaitingLow>),
-    AwaitingHigh(DCF77Parser<Tim, AwaitingHigh>),
+impl From<DCF77Parser<AwaitingLow>> for DCF77Parser<Unknown> {
+    fn from(old: DCF77Parser<AwaitingLow>) -> Self {
+        DCF77Parser {
+            current_bits: old.current_bits,
+            current_unknown: old.current_unknown,
+            current_len: old.current_len,
+            next_bits: 0,
+            next_unknown: 0,
+            state: Unknown {},
+        }
+    }
+}
+
+enum DCF77StateWrapper {
+    Unknown(DCF77Parser<Unknown>),
+    AwaitingLow(DCF77Parser<AwaitingLow>),
+    AwaitingHigh(DCF77Parser<AwaitingHigh>),
 }
 
-impl<Tim: Counter> DCF77StateWrapper<Tim> {
-    pub fn new(timer: Tim) -> Self {
-        DCF77StateWrapper::Unknown(DCF77Parser::new(timer))
+impl DCF77StateWrapper {
+    pub fn new() -> Self {
+        DCF77StateWrapper::Unknown(DCF77Parser::new())
     }
 
     pub fn current_bits(&self) -> u64 {
@@ -129,133 +407,731 @@ impl<Tim: Counter> DCF77StateWrapper<Tim> {
         }
     }
 
-    pub fn update(self, rising_edge: bool) -> Self {
+    pub fn current_unknown(&self) -> u64 {
+        match self {
+            DCF77StateWrapper::Unknown(dcf77) => dcf77.current_unknown,
+            DCF77StateWrapper::AwaitingHigh(dcf77) => dcf77.current_unknown,
+            DCF77StateWrapper::AwaitingLow(dcf77) => dcf77.current_unknown,
+        }
+    }
+
+    pub fn current_len(&self) -> usize {
+        match self {
+            DCF77StateWrapper::Unknown(dcf77) => dcf77.current_len,
+            DCF77StateWrapper::AwaitingHigh(dcf77) => dcf77.current_len,
+            DCF77StateWrapper::AwaitingLow(dcf77) => dcf77.current_len,
+        }
+    }
+
+    /// A minute marker has been seen with the last edge
+    pub fn minute_started(&self) -> bool {
+        matches!(self, DCF77StateWrapper::AwaitingHigh(dcf77) if dcf77.state.bit == 0)
+    }
+
+    /// Process an edge `time` ms after the previous one
+    pub fn update(
+        self,
+        rising_edge: bool,
+        time: u32,
+        classifier: &mut PulseClassifier,
+        stats: &mut ReceptionStats,
+    ) -> Self {
         if rising_edge {
             // going up, end of data.
             match self {
-                DCF77StateWrapper::Unknown(mut dcf77) => {
+                DCF77StateWrapper::Unknown(dcf77) => {
                     //hprintln!("?").unwrap_or(());
-                    dcf77.timer.restart();
                     DCF77StateWrapper::Unknown(dcf77)
                 }
-                DCF77StateWrapper::AwaitingHigh(mut dcf77) => {
-                    let time_ms = dcf77.timer.restart();
-                    if time_ms < 150 {
+                DCF77StateWrapper::AwaitingHigh(dcf77) => {
+                    let pulse = classifier.classify(time);
+                    stats.pulse(pulse);
+                    match pulse {
                         //hprintln!("0").unwrap_or(());
-                        DCF77StateWrapper::AwaitingLow(dcf77.update(false))
-                    } else if time_ms < 250 {
+                        Pulse::Zero => DCF77StateWrapper::AwaitingLow(dcf77.update(Some(false))),
                         //hprintln!("1").unwrap_or(());
-                        DCF77StateWrapper::AwaitingLow(dcf77.update(true))
-                    } else {
-                        DCF77StateWrapper::Unknown(dcf77.into())
+                        Pulse::One => DCF77StateWrapper::AwaitingLow(dcf77.update(Some(true))),
+                        // keep track of the second, the bit might be recovered
+                        Pulse::Invalid => DCF77StateWrapper::AwaitingLow(dcf77.update(None)),
+                        Pulse::Glitch if dcf77.state.bit > 0 => {
+                            DCF77StateWrapper::AwaitingLow(dcf77.glitch(time))
+                        }
+                        _ => DCF77StateWrapper::Unknown(dcf77.into()),
                     }
                 }
-                DCF77StateWrapper::AwaitingLow(mut dcf77) => {
-                    dcf77.timer.restart();
-                    DCF77StateWrapper::Unknown(dcf77.into())
-                }
+                DCF77StateWrapper::AwaitingLow(dcf77) => DCF77StateWrapper::Unknown(dcf77.into()),
             }
         } else {
             // going down, begin of new second, begin of data
             match self {
-                DCF77StateWrapper::Unknown(mut dcf77) => {
-                    let time = dcf77.timer.restart();
-                    if time > 1800 && time < 2200 {
-                        DCF77StateWrapper::AwaitingHigh(dcf77.start_minute())
+                DCF77StateWrapper::Unknown(dcf77) => {
+                    if classifier.is_minute_gap(time) {
+                        // the previous minute was not received completely
+                        DCF77StateWrapper::AwaitingHigh(dcf77.start_minute(0, time))
                     } else {
                         DCF77StateWrapper::Unknown(dcf77)
                     }
                 }
-                DCF77StateWrapper::AwaitingLow(mut dcf77) => {
-                    let time = dcf77.timer.restart();
-                    if time > 1800 && time < 2200 {
-                        DCF77StateWrapper::AwaitingHigh(dcf77.start_minute())
+                DCF77StateWrapper::AwaitingLow(dcf77) => {
+                    let time = time + dcf77.state.carry;
+                    if classifier.is_minute_gap(time) {
+                        let len = dcf77.state.bit + 1;
+                        DCF77StateWrapper::AwaitingHigh(dcf77.start_minute(len, time))
+                    } else if dcf77.state.bit < 59 {
+                        DCF77StateWrapper::AwaitingHigh(dcf77.next_second(time))
                     } else {
-                        DCF77StateWrapper::AwaitingHigh(dcf77.into())
+                        // no minute marker after 60 seconds, we lost track
+                        DCF77StateWrapper::Unknown(dcf77.into())
                     }
                 }
-                DCF77StateWrapper::AwaitingHigh(mut dcf77) => {
-                    dcf77.timer.restart();
-                    DCF77StateWrapper::Unknown(dcf77.into())
-                }
+                DCF77StateWrapper::AwaitingHigh(dcf77) => DCF77StateWrapper::Unknown(dcf77.into()),
             }
         }
     }
 }
 
-pub struct DCF77<Timer: Counter> {
-    state: DCF77StateWrapper<Timer>,
-    pin: Pin<Input<PullUp>>,
-    inverted: bool,
+/// Decoder of the DCF77 signal
+///
+/// Fed with the edges of the receiver signal, where a falling edge marks the
+/// start of a pulse.
+/// Frame expected for a minute, predicted from the last confirmed one
+#[derive(Copy, Clone, Debug)]
+struct Prediction {
+    /// frame ending with the last minute marker
+    frame: DCF77Frame,
+    /// time since the last minute marker
+    since_marker_ms: u32,
+    /// time since the confirmed frame the prediction started from
+    age_ms: u32,
 }
 
-impl<Timer: Counter> DCF77<Timer> {
-    pub fn init(timer: Timer, pin: Pin<Input<PullUp>>, inverted: bool) -> Self {
+impl Prediction {
+    fn new(frame: DCF77Frame) -> Self {
+        Self {
+            frame,
+            since_marker_ms: 0,
+            age_ms: 0,
+        }
+    }
+
+    fn elapse(self, delta_ms: u32) -> Self {
+        Self {
+            since_marker_ms: self.since_marker_ms.saturating_add(delta_ms),
+            age_ms: self.age_ms.saturating_add(delta_ms),
+            ..self
+        }
+    }
+
+    /// Advance to the frame ending with the minute marker just received
+    ///
+    /// Usually a single minute, more if the markers in between were lost.
+    fn minute_marker(self) -> Option<Self> {
+        let minutes = (self.since_marker_ms + 30_000) / 60_000;
+        if minutes == 0 {
+            return Some(self);
+        }
+
+        let mut frame = self.frame;
+        for _ in 0..minutes {
+            frame = frame.next_minute().ok()?;
+        }
+        Some(Self {
+            frame,
+            since_marker_ms: 0,
+            ..self
+        })
+    }
+}
+
+pub struct DCF77 {
+    state: DCF77StateWrapper,
+    filter: EdgeFilter,
+    classifier: PulseClassifier,
+    plausibility: Plausibility,
+    phase: PhaseTracker,
+    stats: ReceptionStats,
+    /// the last input edge started a second in phase
+    second_started: bool,
+    /// the last complete frame
+    frame: Result<DCF77Frame, Error>,
+    /// frame expected for the minute ending with the next marker
+    prediction: Option<Prediction>,
+    max_corrections: Option<u32>,
+}
+
+impl DCF77 {
+    /// `required_frames` consecutive frames have to match before a time is
+    /// returned by `now()`
+    pub fn init(classifier: PulseClassifier, required_frames: u8) -> Self {
         DCF77 {
-            state: DCF77StateWrapper::new(timer),
-            pin,
-            inverted,
+            state: DCF77StateWrapper::new(),
+            filter: EdgeFilter::new(classifier.glitch_ms()),
+            classifier,
+            plausibility: Plausibility::new(required_frames),
+            phase: PhaseTracker::new(),
+            stats: ReceptionStats::default(),
+            second_started: false,
+            frame: Err(Error::StartNotFound),
+            prediction: None,
+            max_corrections: None,
         }
     }
 
-    pub fn update_state(&mut self) -> Result<(), Error> {
-        let rising_edge = self.pin.is_high().unwrap() ^ self.inverted;
+    /// Repair frames against the one predicted from the last confirmed frame
+    ///
+    /// A frame is accepted if up to `max_errors` bits of the time information
+    /// are wrong or could not be read. `None` only accepts error free frames.
+    /// The last confirmed frame is kept through minutes which could not be
+    /// decoded, for up to a day.
+    pub fn set_correction(&mut self, max_errors: Option<u32>) {
+        self.max_corrections = max_errors;
+    }
 
-        replace_with(
-            &mut self.state,
-            || panic!(""),
-            |state| state.update(rising_edge),
-        );
+    pub fn filter_stats(&self) -> FilterStats {
+        self.filter.stats()
+    }
+
+    pub fn classifier(&self) -> &PulseClassifier {
+        &self.classifier
+    }
+
+    /// The last complete frame
+    pub fn frame(&self) -> nb::Result<DCF77Frame, Error> {
+        if let DCF77StateWrapper::Unknown(_) = self.state {
+            return Err(WouldBlock);
+        }
+
+        Ok(self.frame?)
+    }
 
-        Ok(())
+    pub fn phase(&self) -> &PhaseTracker {
+        &self.phase
     }
 
-    pub fn now(&self) -> nb::Result<NaiveDateTime, Error> {
-        let second = match &self.state {
-            DCF77StateWrapper::Unknown(_) => return Err(WouldBlock),
-            DCF77StateWrapper::AwaitingHigh(dcf77) => dcf77.state.bit,
-            DCF77StateWrapper::AwaitingLow(dcf77) => dcf77.state.bit,
-        } as u32;
+    /// Decode the frame just completed, repairing it if enabled
+    fn decode_frame(&mut self) -> Result<DCF77Frame, Error> {
+        let bits = self.state.current_bits();
+        let unknown = self.state.current_unknown();
+        let len = self.state.current_len();
 
-        self.valid()?;
+        match (
+            decode(bits, unknown, len),
+            self.max_corrections,
+            self.prediction,
+        ) {
+            (Err(error), Some(max_errors), Some(prediction)) => {
+                let frame =
+                    correct(bits, unknown, len, &prediction.frame, max_errors).ok_or(error)?;
+                self.stats.corrected_frames = self.stats.corrected_frames.wrapping_add(1);
+                Ok(frame)
+            }
+            (result, _, _) => result,
+        }
+    }
+}
+
+impl TimeSignal for DCF77 {
+    fn update(&mut self, rising_edge: bool, delta_ms: u32) {
+        self.stats.edge(delta_ms);
+        self.second_started = false;
+        self.prediction = self
+            .prediction
+            .map(|prediction| prediction.elapse(delta_ms))
+            .filter(|prediction| prediction.age_ms <= MAX_PREDICTION_MS);
+        for edge in self.filter.update(rising_edge, delta_ms).iter().flatten() {
+            let marker = self.phase.edge(!edge.rising, edge.delta_ms);
+            let classifier = &mut self.classifier;
+            let stats = &mut self.stats;
+            replace_with(
+                &mut self.state,
+                || panic!(""),
+                |state| state.update(edge.rising, edge.delta_ms, classifier, stats),
+            );
 
-        fn extract_number(bits: u64, fst: usize, tens: usize) -> u32 {
-            (bits.get_bits(fst..(fst + 4)) + bits.get_bits((fst + 5)..(fst + 5 + tens)) * 10)
-                .try_into()
-                .unwrap()
+            if self.state.minute_started() {
+                self.prediction = self.prediction.and_then(Prediction::minute_marker);
+                self.frame = self.decode_frame();
+                // a minute marker seen without preceding frame is no error
+                if self.state.current_len() > 0 {
+                    self.stats.frame(self.frame);
+                }
+                self.plausibility
+                    .update(self.frame.and_then(|frame| frame.utc(0)));
+                // keep the previous prediction through unreadable minutes
+                if let (Some(_), Ok(frame)) = (self.plausibility.confirmed(), self.frame) {
+                    self.prediction = Some(Prediction::new(frame));
+                    self.phase
+                        .announce_leap_second(frame.leap_second_announcement);
+                }
+                if marker {
+                    self.phase.minute_marker(0, self.plausibility.confirmed());
+                }
+            }
+            self.second_started = marker;
         }
+    }
+
+    fn second_started(&self) -> bool {
+        self.second_started
+    }
 
-        let curr_bits = self.state.current_bits();
+    /// Time of the current second in UTC
+    ///
+    /// Available once a minute has been confirmed, and kept up to date from
+    /// the second markers as long as the phase stays locked, even if the
+    /// following frames can not be decoded.
+    fn now(&self) -> nb::Result<NaiveDateTime, Error> {
+        self.phase.now().ok_or(WouldBlock)
+    }
 
-        let minute = extract_number(curr_bits, 21, 3);
-        let hour = extract_number(curr_bits, 29, 2);
-        let day = extract_number(curr_bits, 36, 2);
-        let month = extract_number(curr_bits, 45, 1);
-        let year = extract_number(curr_bits, 50, 4).try_into().unwrap();
+    fn leap_second(&self) -> bool {
+        self.phase.leap_second_due()
+    }
 
-        let date = NaiveDate::from_ymd_opt(year, month, day).ok_or(Error::InvalidDate)?;
-        date.and_hms_opt(hour, minute, second)
-            .ok_or(nb::Error::Other(Error::InvalidTime))
+    fn confidence(&self) -> Confidence {
+        self.plausibility.confidence()
     }
 
-    fn valid(&self) -> Result<(), Error> {
-        const PARITY_RANGES: [(RangeInclusive<usize>, Error); 3] = [
-            (21..=28, Error::ParityErrorMinute),
-            (29..=35, Error::ParityErrorHour),
-            (36..=58, Error::ParityErrorDate),
-        ];
+    fn stats(&self) -> &ReceptionStats {
+        &self.stats
+    }
+}
+
+/// Validate and decode a frame of `len` bits, `unknown` marks unreadable bits
+pub fn decode(bits: u64, unknown: u64, len: usize) -> Result<DCF77Frame, Error> {
+    // all bits except for the encrypted weather information
+    const USED_BITS: u64 = 0x0FFF_FFFF_FFFF_8001;
+
+    if unknown & USED_BITS != 0 {
+        return Err(Error::UnreadableBit);
+    }
+    validate(bits, len)?;
+    DCF77Frame::from_bits(bits)
+}
+
+/// Repair a frame against the `expected` one
+///
+/// Wrong and unreadable bits of the time information are taken from the
+/// expected frame, as long as there are at most `max_errors` of them.
+/// Unreadable flags are taken from the expected frame as well.
+pub fn correct(
+    bits: u64,
+    unknown: u64,
+    len: usize,
+    expected: &DCF77Frame,
+    max_errors: u32,
+) -> Option<DCF77Frame> {
+    let mut time_mask = 0u64;
+    time_mask.set_bits(TIME_BITS, (1 << TIME_BITS.clone().count()) - 1);
+    let expected_bits = expected.to_bits();
+
+    let errors = (((bits ^ expected_bits) | unknown) & time_mask).count_ones();
+    if errors > max_errors {
+        return None;
+    }
+
+    let replaced = time_mask | unknown;
+    let repaired = (expected_bits & replaced) | (bits & !replaced);
+    decode(repaired, 0, len).ok()
+}
+
+/// Check the structure of a received frame of `len` bits
+///
+/// This verifies everything that can be checked without interpreting the
+/// transmitted time, so a garbage frame never reaches `DCF77Frame::from_bits`.
+pub fn validate(bits: u64, len: usize) -> Result<(), Error> {
+    // (first bit, width) of all four bit wide BCD digits
+    const BCD_DIGITS: [(usize, usize); 6] = [(21, 4), (29, 4), (36, 4), (45, 4), (50, 4), (54, 4)];
+
+    match len {
+        59 => (),
+        // leap second, only allowed if announced and always transmitted as zero
+        60 if bits.get_bit(19) && !bits.get_bit(59) => (),
+        _ => return Err(Error::ProtocolError),
+    }
+
+    if bits.get_bit(0) {
+        return Err(Error::StartOfMinuteError);
+    }
+    if !bits.get_bit(20) {
+        return Err(Error::StartOfTimeError);
+    }
+    if bits.get_bit(17) == bits.get_bit(18) {
+        return Err(Error::TimezoneError);
+    }
+
+    for (bit_range, error) in PARITY_RANGES.iter() {
+        let checksum = bit_range
+            .clone()
+            .map(|bit| bits.get_bit(bit))
+            .fold(false, |acc, x| acc ^ x);
+        // we expect an even parity
+        if checksum {
+            return Err(*error);
+        }
+    }
+
+    for &(fst, width) in BCD_DIGITS.iter() {
+        if bits.get_bits(fst..(fst + width)) > 9 {
+            return Err(Error::InvalidBCD);
+        }
+    }
+
+    check_ranges(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday, 2024-02-28 13:37 CET
+    fn frame() -> DCF77Frame {
+        DCF77Frame {
+            call_bit: false,
+            dst_announcement: false,
+            cest: false,
+            cet: true,
+            leap_second_announcement: false,
+            minute: 37,
+            hour: 13,
+            day: 28,
+            weekday: Weekday::Wed,
+            month: 2,
+            year: 2024,
+        }
+    }
+
+    /// `bits` with the parity bits matching the rest
+    fn with_parity(mut bits: u64) -> u64 {
+        for (bit_range, _) in PARITY_RANGES.iter() {
+            let parity = (*bit_range.start()..*bit_range.end())
+                .fold(false, |acc, bit| acc ^ bits.get_bit(bit));
+            bits.set_bit(*bit_range.end(), parity);
+        }
+        bits
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let bits = frame().to_bits();
+        assert_eq!(validate(bits, 59), Ok(()));
+        assert_eq!(DCF77Frame::from_bits(bits), Ok(frame()));
+        assert_eq!(decode(bits, 0, 59), Ok(frame()));
+        assert_eq!(
+            frame().utc(0),
+            Ok(NaiveDate::from_ymd(2024, 2, 28).and_hms(12, 37, 0))
+        );
+    }
 
+    #[test]
+    fn validate_length() {
+        let bits = frame().to_bits();
+        assert_eq!(validate(bits, 58), Err(Error::ProtocolError));
+        assert_eq!(validate(bits, 61), Err(Error::ProtocolError));
+        // a leap second has to be announced and transmitted as zero
+        assert_eq!(validate(bits, 60), Err(Error::ProtocolError));
+        let leap = DCF77Frame {
+            leap_second_announcement: true,
+            ..frame()
+        }
+        .to_bits();
+        assert_eq!(validate(leap, 60), Ok(()));
+        assert_eq!(validate(leap | 1 << 59, 60), Err(Error::ProtocolError));
+    }
+
+    #[test]
+    fn validate_markers() {
+        let bits = frame().to_bits();
+        assert_eq!(validate(bits | 1, 59), Err(Error::StartOfMinuteError));
+        assert_eq!(
+            validate(bits & !(1 << 20), 59),
+            Err(Error::StartOfTimeError)
+        );
+        assert_eq!(validate(bits | 1 << 17, 59), Err(Error::TimezoneError));
+        assert_eq!(validate(bits & !(1 << 18), 59), Err(Error::TimezoneError));
+    }
+
+    #[test]
+    fn validate_parity() {
+        let bits = frame().to_bits();
         for (bit_range, error) in PARITY_RANGES.iter() {
-            let checksum = bit_range
-                .clone()
-                .map(|bit| self.state.current_bits().get_bit(bit))
-                .fold(false, |acc, x| acc ^ x);
-            // we expect an even parity
-            if checksum == true {
-                return Err(*error);
+            for bit in bit_range.clone() {
+                assert_eq!(validate(bits ^ 1 << bit, 59), Err(*error), "bit {}", bit);
+            }
+        }
+    }
+
+    #[test]
+    fn validate_bcd() {
+        let bits = frame().to_bits();
+        for &fst in [21, 29, 36, 45, 50, 54].iter() {
+            for digit in 10..16 {
+                let mut invalid = bits;
+                invalid.set_bits(fst..(fst + 4), digit);
+                assert_eq!(
+                    validate(with_parity(invalid), 59),
+                    Err(Error::InvalidBCD),
+                    "digit {} at {}",
+                    digit,
+                    fst
+                );
             }
         }
+    }
+
+    #[test]
+    fn validate_ranges() {
+        let bits = frame().to_bits();
+        // (ones, bits of the tens, value) of each number out of its range
+        let invalid = [
+            (21, 3, 60, Error::InvalidMinute),
+            (21, 3, 79, Error::InvalidMinute),
+            (29, 2, 24, Error::InvalidHour),
+            (29, 2, 39, Error::InvalidHour),
+            (36, 2, 0, Error::InvalidDay),
+            (36, 2, 32, Error::InvalidDay),
+            (45, 1, 0, Error::InvalidMonth),
+            (45, 1, 13, Error::InvalidMonth),
+        ];
+        for &(fst, tens, value, error) in invalid.iter() {
+            let mut invalid = bits;
+            insert_number(&mut invalid, fst, tens, value);
+            let invalid = with_parity(invalid);
+            assert_eq!(validate(invalid, 59), Err(error), "{} at {}", value, fst);
+            assert_eq!(DCF77Frame::from_bits(invalid), Err(error));
+        }
+
+        let mut invalid = bits;
+        invalid.set_bits(42..45, 0);
+        let invalid = with_parity(invalid);
+        assert_eq!(validate(invalid, 59), Err(Error::InvalidWeekday));
+        assert_eq!(DCF77Frame::from_bits(invalid), Err(Error::InvalidWeekday));
+    }
+
+    #[test]
+    fn century() {
+        let ymd = |year, month, day| NaiveDate::from_ymd(year, month, day);
+        let century = |year, weekday, month, day| {
+            infer_century(year, weekday, |year| {
+                NaiveDate::from_ymd_opt(year, month, day)
+            })
+        };
+        assert_eq!(century(0, Weekday::Sat, 1, 1), Ok(ymd(2000, 1, 1)));
+        assert_eq!(century(99, Weekday::Thu, 12, 31), Ok(ymd(2099, 12, 31)));
+        assert_eq!(century(0, Weekday::Fri, 1, 1), Ok(ymd(2100, 1, 1)));
+        assert_eq!(century(69, Weekday::Sun, 12, 31), Ok(ymd(2169, 12, 31)));
+        assert_eq!(century(99, Weekday::Tue, 12, 31), Ok(ymd(2199, 12, 31)));
+        // outside of the window
+        assert_eq!(century(99, Weekday::Fri, 12, 31), Err(Error::InvalidDate));
+
+        // 2100 is no leap year, unlike 2000
+        assert_eq!(century(0, Weekday::Tue, 2, 29), Ok(ymd(2000, 2, 29)));
+        assert_eq!(century(0, Weekday::Mon, 2, 29), Err(Error::InvalidDate));
+        // neither 2099 nor 2199 ends on a Monday
+        assert_eq!(century(99, Weekday::Mon, 12, 31), Err(Error::InvalidDate));
+    }
+
+    #[test]
+    fn weekday_not_matching_date() {
+        let bits = |weekday| DCF77Frame { weekday, ..frame() }.to_bits();
+        assert_eq!(validate(bits(Weekday::Tue), 59), Ok(()));
+        assert_eq!(decode(bits(Weekday::Tue), 0, 59), Err(Error::InvalidDate));
+        // a wrong weekday never moves the date by centuries
+        for &weekday in [Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun].iter() {
+            assert_eq!(decode(bits(weekday), 0, 59), Err(Error::InvalidDate));
+        }
+        // except to the neighbouring one, where the date falls on it
+        assert_eq!(
+            decode(bits(Weekday::Mon), 0, 59).map(|frame| frame.year),
+            Ok(2124)
+        );
+    }
+
+    #[test]
+    fn dst_change() {
+        // 2024-03-31 01:59 CET is followed by 03:00 CEST
+        let before = DCF77Frame {
+            dst_announcement: true,
+            minute: 59,
+            hour: 1,
+            day: 31,
+            weekday: Weekday::Sun,
+            month: 3,
+            ..frame()
+        };
+        let after = DCF77Frame {
+            dst_announcement: false,
+            cest: true,
+            cet: false,
+            minute: 0,
+            hour: 3,
+            ..before
+        };
+        assert_eq!(before.next_minute(), Ok(after));
+        assert_eq!(
+            after.utc(0),
+            Ok(before.utc(0).unwrap() + Duration::minutes(1))
+        );
+
+        // 2024-10-27 02:59 CEST is followed by 02:00 CET
+        let before = DCF77Frame {
+            cest: true,
+            cet: false,
+            day: 27,
+            month: 10,
+            hour: 2,
+            ..before
+        };
+        let after = DCF77Frame {
+            dst_announcement: false,
+            cest: false,
+            cet: true,
+            minute: 0,
+            hour: 2,
+            ..before
+        };
+        assert_eq!(before.next_minute(), Ok(after));
+        assert_eq!(
+            after.utc(0),
+            Ok(before.utc(0).unwrap() + Duration::minutes(1))
+        );
+
+        // the announcement is sent during the whole hour before
+        let earlier = DCF77Frame {
+            minute: 30,
+            ..before
+        };
+        assert_eq!(
+            earlier
+                .next_minute()
+                .map(|frame| (frame.hour, frame.minute, frame.dst_announcement)),
+            Ok((2, 31, true))
+        );
+
+        // the leap second announcement ends with the hour as well
+        let leap_second = DCF77Frame {
+            leap_second_announcement: true,
+            minute: 58,
+            hour: 0,
+            day: 1,
+            weekday: Weekday::Mon,
+            month: 7,
+            year: 2024,
+            ..frame()
+        };
+        let next = leap_second.next_minute().unwrap();
+        assert!(next.leap_second_announcement);
+        let next = next.next_minute().unwrap();
+        assert_eq!((next.hour, next.minute), (1, 0));
+        assert!(!next.leap_second_announcement);
+        assert!(!next.next_minute().unwrap().leap_second_announcement);
+    }
+
+    #[test]
+    fn correction_across_dst_change() {
+        let before = DCF77Frame {
+            dst_announcement: true,
+            minute: 59,
+            hour: 1,
+            day: 31,
+            weekday: Weekday::Sun,
+            month: 3,
+            ..frame()
+        };
+        let after = DCF77Frame {
+            dst_announcement: false,
+            cest: true,
+            cet: false,
+            minute: 0,
+            hour: 3,
+            ..before
+        };
+        // a disturbed frame is repaired to the time after the change
+        let bits = after.to_bits() ^ 1 << 22;
+        let expected = before.next_minute().unwrap();
+        assert_eq!(correct(bits, 0, 59, &expected, 2), Some(after));
+    }
+
+    /// Send the pulses of `bits` to `decoder`, ending with the minute marker
+    ///
+    /// A spike of 10ms is added 400ms into each second listed in `spikes`.
+    fn transmit(decoder: &mut DCF77, bits: u64, spikes: &[usize]) {
+        for bit in 0..59 {
+            let width = if bits.get_bit(bit) { 200 } else { 100 };
+            decoder.update(true, width);
+            let pause = if bit == 58 { 2000 } else { 1000 } - width;
+            if spikes.contains(&bit) {
+                decoder.update(false, 400 - width);
+                decoder.update(true, 10);
+                decoder.update(false, pause - 410 + width);
+            } else {
+                decoder.update(false, pause);
+            }
+        }
+    }
+
+    #[test]
+    fn glitch_keeps_state() {
+        let mut decoder = DCF77::init(PulseClassifier::default(), 1);
+        // the end of the previous minute
+        decoder.update(true, 100);
+        decoder.update(false, 1900);
+        transmit(&mut decoder, frame().to_bits(), &[0, 17, 30, 57]);
+        assert_eq!(decoder.frame(), Ok(frame()));
+        assert_eq!(decoder.filter_stats().short_pulses, 4);
+        assert_eq!(decoder.stats().valid_bits, 59);
+        assert_eq!(decoder.stats().invalid_pulses, 0);
+    }
+
+    #[test]
+    fn correction_after_bad_minutes() {
+        let mut decoder = DCF77::init(PulseClassifier::default(), 2);
+        decoder.set_correction(Some(2));
+        decoder.update(true, 100);
+        decoder.update(false, 1900);
+
+        let mut frame = frame();
+        for _ in 0..2 {
+            transmit(&mut decoder, frame.to_bits(), &[]);
+            frame = frame.next_minute().unwrap();
+        }
+        assert_eq!(decoder.confidence(), Confidence::Confirmed);
+
+        // beyond repair
+        for _ in 0..3 {
+            transmit(&mut decoder, frame.to_bits() ^ 0x1F << 21, &[]);
+            assert_eq!(decoder.frame(), Err(Error::ParityErrorMinute.into()));
+            frame = frame.next_minute().unwrap();
+        }
+        transmit(&mut decoder, frame.to_bits() ^ 1 << 30 ^ 1 << 40, &[]);
+        assert_eq!(decoder.frame(), Ok(frame));
+        assert_eq!(decoder.stats().corrected_frames, 1);
+    }
+
+    #[test]
+    fn prediction_across_lost_markers() {
+        let next = Prediction::new(frame()).elapse(60_100).minute_marker();
+        let next = next.unwrap();
+        assert_eq!(
+            (next.frame, next.since_marker_ms),
+            (frame().next_minute().unwrap(), 0)
+        );
+        // a spurious marker within the minute does not advance it
+        let spurious = next.elapse(20_000).minute_marker().unwrap();
+        assert_eq!(
+            (spurious.frame, spurious.since_marker_ms),
+            (next.frame, 20_000)
+        );
+        // three minutes without a marker
+        let later = next.elapse(180_000).minute_marker().unwrap();
+        assert_eq!((later.frame.minute, later.age_ms), (41, 240_100));
+    }
 
-        Ok(())
+    #[test]
+    fn unreadable_bits() {
+        let bits = frame().to_bits();
+        assert_eq!(decode(bits, 1 << 30, 59), Err(Error::UnreadableBit));
+        // the weather information is not needed
+        assert_eq!(decode(bits, 1 << 5, 59), Ok(frame()));
     }
 }
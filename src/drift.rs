@@ -0,0 +1,182 @@
+use crate::sync::SyncResult;
+use chrono::NaiveDateTime;
+
+/// Minimum time between two references for a drift measurement
+///
+/// The offset is measured with a resolution of about 4ms, it takes a few
+/// hours until this is well below one step of the smooth calibration.
+const MIN_INTERVAL_S: i64 = 6 * 60 * 60;
+/// Inverse weight of a new measurement in the filtered estimate
+const FILTER_WEIGHT: i64 = 4;
+/// Limits of the smooth calibration, -511 to +512 pulses in 2^20
+const MIN_CORRECTION_PPB: i64 = -487_344;
+const MAX_CORRECTION_PPB: i64 = 488_281;
+
+/// Estimation of the correction required to compensate the RTC drift
+///
+/// Every synchronisation provides the offset between RTC and reference. The
+/// drift is derived from the change of this offset between two references,
+/// relative to the correction active during the interval.
+pub struct DriftEstimator {
+    /// time and remaining offset of the RTC at the last reference
+    reference: Option<(NaiveDateTime, i64)>,
+    /// filtered correction in parts per billion, positive speeds the RTC up
+    correction_ppb: Option<i32>,
+}
+
+impl DriftEstimator {
+    pub fn new(correction_ppb: Option<i32>) -> Self {
+        Self {
+            reference: None,
+            correction_ppb,
+        }
+    }
+
+    pub fn correction_ppb(&self) -> Option<i32> {
+        self.correction_ppb
+    }
+
+    /// Forget the reference, e.g. after the RTC has been set manually
+    pub fn reset(&mut self) {
+        self.reference = None;
+    }
+
+    /// Add the result of a synchronisation at reference `time`
+    ///
+    /// Returns the new correction if it has been updated.
+    pub fn update(&mut self, time: NaiveDateTime, sync: SyncResult) -> Option<i32> {
+        let (ref_time, ref_offset_ms) = match self.reference {
+            Some(reference) => reference,
+            None => {
+                self.reference = Some((time, sync.remaining_offset_ms()));
+                return None;
+            }
+        };
+
+        let interval_s = (time - ref_time).num_seconds();
+        if interval_s < MIN_INTERVAL_S {
+            // keep the reference across a jump of the RTC
+            let jump_ms = sync.offset_ms() - sync.remaining_offset_ms();
+            self.reference = Some((ref_time, ref_offset_ms - jump_ms));
+            return None;
+        }
+
+        // 1ms/s equals 1000ppm
+        let drift_ppb = (sync.offset_ms() - ref_offset_ms) * 1_000_000 / interval_s;
+        let correction = match self.correction_ppb {
+            Some(current) => current as i64 - drift_ppb / FILTER_WEIGHT,
+            None => -drift_ppb,
+        };
+        let correction = correction.clamp(MIN_CORRECTION_PPB, MAX_CORRECTION_PPB) as i32;
+
+        self.correction_ppb = Some(correction);
+        self.reference = Some((time, sync.remaining_offset_ms()));
+        Some(correction)
+    }
+}
+
+/// Smooth calibration value for `correction_ppb` as (CALP, CALM)
+///
+/// The calibration masks CALM out of 2^20 RTCCLK pulses, CALP inserts 512.
+pub fn calibration_register(correction_ppb: i32) -> (bool, u16) {
+    let pulses = (correction_ppb as i64 * (1 << 20) / 1_000_000_000) as i32;
+    if pulses > 0 {
+        (true, (512 - pulses.min(512)) as u16)
+    } else {
+        (false, (-pulses).min(511) as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    fn start() -> NaiveDateTime {
+        NaiveDate::from_ymd(2024, 2, 28).and_hms(12, 0, 0)
+    }
+
+    fn hours(hours: i64) -> NaiveDateTime {
+        start() + Duration::hours(hours)
+    }
+
+    #[test]
+    fn single_interval() {
+        let mut estimator = DriftEstimator::new(None);
+        let in_sync = |offset_ms| SyncResult::InSync { offset_ms };
+        assert_eq!(estimator.update(start(), in_sync(0)), None);
+        // too short to measure the drift
+        assert_eq!(estimator.update(hours(1), in_sync(10)), None);
+        assert_eq!(estimator.correction_ppb(), None);
+        // 43.2ms in 6h are 2ppm fast, which are compensated completely
+        assert_eq!(estimator.update(hours(6), in_sync(43)), Some(-1990));
+        assert_eq!(estimator.correction_ppb(), Some(-1990));
+    }
+
+    #[test]
+    fn jump_within_interval() {
+        let mut estimator = DriftEstimator::new(None);
+        estimator.update(start(), SyncResult::InSync { offset_ms: 0 });
+        // 100ppm fast, the RTC is set back after an hour
+        estimator.update(hours(1), SyncResult::Updated { offset_ms: 360 });
+        let result = SyncResult::Updated { offset_ms: 1800 };
+        assert_eq!(estimator.update(hours(6), result), Some(-100_000));
+    }
+
+    #[test]
+    fn filtered() {
+        let mut estimator = DriftEstimator::new(Some(-100_000));
+        estimator.update(start(), SyncResult::InSync { offset_ms: 0 });
+        // the remaining drift of 20ppm is corrected by a quarter
+        let result = SyncResult::Updated { offset_ms: 432 };
+        assert_eq!(estimator.update(hours(6), result), Some(-105_000));
+        // the next interval starts at the reference just set
+        let result = SyncResult::InSync { offset_ms: 324 };
+        assert_eq!(estimator.update(hours(12), result), Some(-108_750));
+        // a single outlier only moves the estimate a bit
+        let result = SyncResult::Updated { offset_ms: -2160 };
+        assert_eq!(estimator.update(hours(18), result), Some(-80_000));
+    }
+
+    #[test]
+    fn reset() {
+        let mut estimator = DriftEstimator::new(Some(1000));
+        estimator.update(start(), SyncResult::InSync { offset_ms: 0 });
+        estimator.reset();
+        // the RTC has been set manually, the next reference starts over
+        let result = SyncResult::Updated { offset_ms: 5000 };
+        assert_eq!(estimator.update(hours(6), result), None);
+        assert_eq!(estimator.correction_ppb(), Some(1000));
+    }
+
+    #[test]
+    fn clamped() {
+        let mut estimator = DriftEstimator::new(None);
+        estimator.update(start(), SyncResult::InSync { offset_ms: 0 });
+        // 1000ppm slow is beyond what the calibration can compensate
+        let result = SyncResult::Updated { offset_ms: -21_600 };
+        assert_eq!(estimator.update(hours(6), result), Some(488_281));
+
+        let mut estimator = DriftEstimator::new(None);
+        estimator.update(start(), SyncResult::InSync { offset_ms: 0 });
+        let result = SyncResult::Updated { offset_ms: 43_200 };
+        assert_eq!(estimator.update(hours(6), result), Some(-487_344));
+    }
+
+    #[test]
+    fn register() {
+        assert_eq!(calibration_register(0), (false, 0));
+        // a single pulse is about 954ppb
+        assert_eq!(calibration_register(900), (false, 0));
+        assert_eq!(calibration_register(954), (true, 511));
+        assert_eq!(calibration_register(-954), (false, 1));
+        assert_eq!(calibration_register(954 * 2), (true, 510));
+        assert_eq!(calibration_register(-100_000), (false, 104));
+        assert_eq!(calibration_register(100_000), (true, 408));
+        // limits of the calibration
+        assert_eq!(calibration_register(-487_344), (false, 511));
+        assert_eq!(calibration_register(488_281), (true, 1));
+        assert_eq!(calibration_register(-1_000_000), (false, 511));
+        assert_eq!(calibration_register(1_000_000), (true, 0));
+    }
+}
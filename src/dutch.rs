@@ -0,0 +1,139 @@
+//! Dutch phrasing, relative to the half hour from twenty past on
+//!
+//! Twenty past two is "tien voor half drie", ten to half three, so the next
+//! hour is named from then on.
+
+use crate::face::{Face, HourName, Phrase, Phrasing};
+use bitflags::bitflags;
+use chrono::NaiveTime;
+
+bitflags! {
+    pub struct MainWord : u32 {
+        const HET_IS = 0x01;
+        const VIJF = 0x02;
+        const TIEN = 0x04;
+        const KWART = 0x08;
+        const VOOR = 0x10;
+        const OVER = 0x20;
+        const HALF = 0x40;
+    }
+}
+
+const FIVE_MINUTE_STATE: [Phrase; 12] = [
+    Phrase {
+        main: MainWord::HET_IS.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits() | MainWord::VIJF.bits() | MainWord::OVER.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits() | MainWord::TIEN.bits() | MainWord::OVER.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits() | MainWord::KWART.bits() | MainWord::OVER.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits()
+            | MainWord::TIEN.bits()
+            | MainWord::VOOR.bits()
+            | MainWord::HALF.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits()
+            | MainWord::VIJF.bits()
+            | MainWord::VOOR.bits()
+            | MainWord::HALF.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits() | MainWord::HALF.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits()
+            | MainWord::VIJF.bits()
+            | MainWord::OVER.bits()
+            | MainWord::HALF.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits()
+            | MainWord::TIEN.bits()
+            | MainWord::OVER.bits()
+            | MainWord::HALF.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits() | MainWord::KWART.bits() | MainWord::VOOR.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits() | MainWord::TIEN.bits() | MainWord::VOOR.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::HET_IS.bits() | MainWord::VIJF.bits() | MainWord::VOOR.bits(),
+        hour_offset: 1,
+    },
+];
+
+/// "het is drie uur" at the full hour
+const PHRASING: Phrasing = Phrasing {
+    steps: &FIVE_MINUTE_STATE,
+    full_hour: true,
+    hour: HourName::NUMBER,
+    hours: &[],
+};
+
+pub fn face(time: NaiveTime) -> Face {
+    PHRASING.face(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::tests::{check_day, fill, phrase, HOUR};
+
+    const HOURS: [&str; 12] = [
+        "twaalf", "een", "twee", "drie", "vier", "vijf", "zes", "zeven", "acht", "negen", "tien",
+        "elf",
+    ];
+
+    fn words(face: &Face) -> String {
+        let words = [
+            (MainWord::HET_IS.bits(), "het is"),
+            (MainWord::VIJF.bits(), "vijf"),
+            (MainWord::TIEN.bits(), "tien"),
+            (MainWord::KWART.bits(), "kwart"),
+            (MainWord::VOOR.bits(), "voor"),
+            (MainWord::OVER.bits(), "over"),
+            (MainWord::HALF.bits(), "half"),
+            (HOUR, ""),
+        ];
+        phrase(face, &words, &HOURS, "uur")
+    }
+
+    #[test]
+    fn five_minute_steps() {
+        let steps = [
+            "het is {} uur",
+            "het is vijf over {}",
+            "het is tien over {}",
+            "het is kwart over {}",
+            "het is tien voor half {+}",
+            "het is vijf voor half {+}",
+            "het is half {+}",
+            "het is vijf over half {+}",
+            "het is tien over half {+}",
+            "het is kwart voor {+}",
+            "het is tien voor {+}",
+            "het is vijf voor {+}",
+        ];
+        check_day(face, words, |hour, step| fill(steps[step], &HOURS, hour));
+    }
+}
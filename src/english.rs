@@ -0,0 +1,131 @@
+//! English phrasing, past the hour up to half past and to the next one after
+
+use crate::face::{Face, HourName, Phrase, Phrasing};
+use bitflags::bitflags;
+use chrono::NaiveTime;
+
+bitflags! {
+    pub struct MainWord : u32 {
+        const IT_IS = 0x01;
+        const FIVE = 0x02;
+        const TEN = 0x04;
+        const QUARTER = 0x08;
+        const TWENTY = 0x10;
+        const HALF = 0x20;
+        const PAST = 0x40;
+        const TO = 0x80;
+    }
+}
+
+const FIVE_MINUTE_STATE: [Phrase; 12] = [
+    Phrase {
+        main: MainWord::IT_IS.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::FIVE.bits() | MainWord::PAST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::TEN.bits() | MainWord::PAST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::QUARTER.bits() | MainWord::PAST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::TWENTY.bits() | MainWord::PAST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits()
+            | MainWord::TWENTY.bits()
+            | MainWord::FIVE.bits()
+            | MainWord::PAST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::HALF.bits() | MainWord::PAST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits()
+            | MainWord::TWENTY.bits()
+            | MainWord::FIVE.bits()
+            | MainWord::TO.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::TWENTY.bits() | MainWord::TO.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::QUARTER.bits() | MainWord::TO.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::TEN.bits() | MainWord::TO.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IT_IS.bits() | MainWord::FIVE.bits() | MainWord::TO.bits(),
+        hour_offset: 1,
+    },
+];
+
+const PHRASING: Phrasing = Phrasing {
+    steps: &FIVE_MINUTE_STATE,
+    full_hour: true,
+    hour: HourName::NUMBER,
+    hours: &[],
+};
+
+pub fn face(time: NaiveTime) -> Face {
+    PHRASING.face(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::tests::{check_day, fill, phrase, HOUR};
+
+    const HOURS: [&str; 12] = [
+        "twelve", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven",
+    ];
+
+    fn words(face: &Face) -> String {
+        let words = [
+            (MainWord::IT_IS.bits(), "it is"),
+            (MainWord::TWENTY.bits(), "twenty"),
+            (MainWord::FIVE.bits(), "five"),
+            (MainWord::TEN.bits(), "ten"),
+            (MainWord::QUARTER.bits(), "quarter"),
+            (MainWord::HALF.bits(), "half"),
+            (MainWord::PAST.bits(), "past"),
+            (MainWord::TO.bits(), "to"),
+            (HOUR, ""),
+        ];
+        phrase(face, &words, &HOURS, "o'clock")
+    }
+
+    #[test]
+    fn five_minute_steps() {
+        let steps = [
+            "it is {} o'clock",
+            "it is five past {}",
+            "it is ten past {}",
+            "it is quarter past {}",
+            "it is twenty past {}",
+            "it is twenty five past {}",
+            "it is half past {}",
+            "it is twenty five to {+}",
+            "it is twenty to {+}",
+            "it is quarter to {+}",
+            "it is ten to {+}",
+            "it is five to {+}",
+        ];
+        check_day(face, words, |hour, step| fill(steps[step], &HOURS, hour));
+    }
+}
@@ -0,0 +1,209 @@
+//! Words lit on the clock face for a time, independent of the hardware
+
+use crate::{dutch, english, french, german, spanish};
+use chrono::{NaiveTime, Timelike};
+
+/// Language of the words on a face
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Language {
+    German,
+    English,
+    Dutch,
+    French,
+    Spanish,
+}
+
+/// Words and minute dots showing a time
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Face {
+    /// words of the phrase, as bits of the `MainWord` of the language
+    pub main: u32,
+    /// hour named, from 0 to 23, or `None` if named by the main words as in
+    /// "MIDI"
+    pub hour: Option<usize>,
+    /// the hour is named alone, as in "UHR" or "O'CLOCK"
+    pub full_hour: bool,
+    /// minutes past the five minute step shown by the words
+    pub dots: u32,
+}
+
+impl Face {
+    /// The variant only applies to German
+    pub fn from_time(time: NaiveTime, language: Language, variant: german::Variant) -> Self {
+        match language {
+            Language::German => german::face(time, variant),
+            Language::English => english::face(time),
+            Language::Dutch => dutch::face(time),
+            Language::French => french::face(time),
+            Language::Spanish => spanish::face(time),
+        }
+    }
+
+    /// Whether the words differ, ignoring the minute dots
+    pub fn words_differ(&self, other: &Face) -> bool {
+        self.main != other.main || self.hour != other.hour || self.full_hour != other.full_hour
+    }
+}
+
+/// Words of a five minute step
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Phrase {
+    /// words of the step, as bits of the `MainWord` of the language
+    pub main: u32,
+    /// hours to add to the current one for the hour named, 1 for "ten to two"
+    pub hour_offset: u32,
+}
+
+/// Words naming an hour
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HourName {
+    /// words added to the phrase, like "HEURES" or "SON LAS"
+    pub main: u32,
+    /// the hour is named by its number, otherwise by `main` alone as "MIDI"
+    pub number: bool,
+    /// a word of the phrase replaced by another, like "DEMIE" by "DEMI"
+    pub replace: Option<(u32, u32)>,
+}
+
+impl HourName {
+    /// The number of the hour without further words
+    pub const NUMBER: HourName = HourName {
+        main: 0,
+        number: true,
+        replace: None,
+    };
+}
+
+/// Phrasing of a language, the words of every step and how hours are named
+pub struct Phrasing {
+    pub steps: &'static [Phrase; 12],
+    /// the hour is named alone at the full hour, as in "UHR" or "O'CLOCK"
+    pub full_hour: bool,
+    /// name of the hours not listed in `hours`
+    pub hour: HourName,
+    /// names of particular hours from 0 to 23, like "MIDI" or "ES LA UNA"
+    pub hours: &'static [(usize, HourName)],
+}
+
+impl Phrasing {
+    pub fn face(&self, time: NaiveTime) -> Face {
+        let step = &self.steps[(time.minute() / 5) as usize];
+        let hour = ((time.hour() + step.hour_offset) % 24) as usize;
+        let name = self
+            .hours
+            .iter()
+            .find(|&&(named, _)| named == hour)
+            .map_or(self.hour, |&(_, name)| name);
+
+        let mut main = step.main | name.main;
+        if let Some((word, replacement)) = name.replace.filter(|&(word, _)| main & word != 0) {
+            main = main & !word | replacement;
+        }
+        Face {
+            main,
+            hour: Some(hour).filter(|_| name.number),
+            full_hour: self.full_hour && time.minute() < 5,
+            dots: dots(time),
+        }
+    }
+}
+
+/// Minutes past the five minute step shown by the words
+pub fn dots(time: NaiveTime) -> u32 {
+    time.minute() % 5
+}
+
+#[cfg(test)]
+pub mod tests {
+    //! Checks of the faces, shared with the tests of the languages
+
+    use super::*;
+
+    /// Stands for the name of the hour in the words given to `phrase`
+    pub const HOUR: u32 = 0;
+
+    /// Words of `face` in the order of `words`, with `full_hour` appended at
+    /// the full hour
+    pub fn phrase(
+        face: &Face,
+        words: &[(u32, &str)],
+        hours: &[&str; 12],
+        full_hour: &str,
+    ) -> String {
+        let mut phrase: Vec<&str> = words
+            .iter()
+            .filter_map(|&(word, name)| match word {
+                HOUR => face.hour.map(|hour| hours[hour % 12]),
+                _ if face.main & word != 0 => Some(name),
+                _ => None,
+            })
+            .collect();
+        if face.full_hour && !full_hour.is_empty() {
+            phrase.push(full_hour);
+        }
+        phrase.join(" ")
+    }
+
+    /// Replace `{}` in `step` by the name of `hour` and `{+}` by the next one
+    pub fn fill(step: &str, hours: &[&str; 12], hour: usize) -> String {
+        step.replace("{}", hours[hour % 12])
+            .replace("{+}", hours[(hour + 1) % 12])
+    }
+
+    /// Check the phrase and the dots of every minute of the day
+    ///
+    /// `expected` gives the phrase of an hour from 0 to 23 and a five minute
+    /// step of it.
+    pub fn check_day(
+        face: impl Fn(NaiveTime) -> Face,
+        phrase: impl Fn(&Face) -> String,
+        expected: impl Fn(usize, usize) -> String,
+    ) {
+        for minute in 0..24 * 60 {
+            let time = NaiveTime::from_hms(minute / 60, minute % 60, 0);
+            let shown = face(time);
+            let (hour, step) = ((minute / 60) as usize, (minute % 60 / 5) as usize);
+            assert_eq!(phrase(&shown), expected(hour, step), "{}", time);
+            assert_eq!(shown.dots, minute % 5, "{}", time);
+        }
+    }
+
+    #[test]
+    fn words_change_every_five_minutes() {
+        let face = |minute: u32| {
+            let time = NaiveTime::from_hms(minute / 60 % 24, minute % 60, 0);
+            Face::from_time(time, Language::German, german::Variant::Standard)
+        };
+        for minute in 0..24 * 60 {
+            let (previous, current) = (face(minute + 24 * 60 - 1), face(minute));
+            assert_eq!(
+                previous.words_differ(&current),
+                minute % 5 == 0,
+                "{}",
+                minute
+            );
+        }
+    }
+
+    #[test]
+    fn seconds_are_ignored() {
+        for minute in 0..24 * 60 {
+            let (hour, minute) = (minute / 60, minute % 60);
+            let face = |second| {
+                let time = NaiveTime::from_hms(hour, minute, second);
+                Face::from_time(time, Language::German, german::Variant::Standard)
+            };
+            assert_eq!(face(0), face(59));
+        }
+    }
+
+    #[test]
+    fn full_hour() {
+        for minute in 0..60 {
+            let time = NaiveTime::from_hms(7, minute, 0);
+            let face = Face::from_time(time, Language::German, german::Variant::Standard);
+            assert_eq!(face.full_hour, minute < 5);
+            assert_eq!(dots(time), minute % 5);
+        }
+    }
+}
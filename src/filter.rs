@@ -0,0 +1,178 @@
+/// A filtered edge of the receiver signal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub rising: bool,
+    /// time since the previous filtered edge
+    pub delta_ms: u32,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FilterStats {
+    /// edges seen on the input
+    pub edges: u32,
+    /// short interruptions of a pulse merged into it
+    pub merged: u32,
+    /// pulses passed on, which are shorter than the threshold
+    pub short_pulses: u32,
+}
+
+/// Glitch filter between the receiver input and the decoder
+///
+/// Falling edges mark the start of a second and are passed on right away to
+/// keep the timing. Rising edges are held back until the next edge, so a short
+/// interruption of a pulse can be merged into it. A too short pulse itself
+/// can only be recognised after its falling edge has been passed on already,
+/// it is left to the decoder to drop it.
+pub struct EdgeFilter {
+    threshold_ms: u32,
+    /// rising edge held back, with the time since the previous filtered edge
+    pending: Option<u32>,
+    /// time since the previous filtered edge, which has not been passed on
+    carry: u32,
+    stats: FilterStats,
+}
+
+impl EdgeFilter {
+    pub fn new(threshold_ms: u32) -> Self {
+        Self {
+            threshold_ms,
+            pending: None,
+            carry: 0,
+            stats: FilterStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> FilterStats {
+        self.stats
+    }
+
+    /// Add an edge `delta_ms` after the previous one
+    ///
+    /// Returns the edges to be passed on to the decoder, in order.
+    pub fn update(&mut self, rising: bool, delta_ms: u32) -> [Option<Edge>; 2] {
+        self.stats.edges = self.stats.edges.wrapping_add(1);
+        let delta_ms = self.carry + delta_ms;
+        self.carry = 0;
+
+        match (self.pending.take(), rising) {
+            (None, true) => {
+                self.pending = Some(delta_ms);
+                [None, None]
+            }
+            (None, false) => [Some(Edge { rising, delta_ms }), None],
+            (Some(pending), false) if delta_ms < self.threshold_ms => {
+                // the pulse continues after a short interruption
+                self.stats.merged = self.stats.merged.wrapping_add(1);
+                self.carry = pending + delta_ms;
+                [None, None]
+            }
+            (Some(pending), rising) => {
+                if pending < self.threshold_ms {
+                    self.stats.short_pulses = self.stats.short_pulses.wrapping_add(1);
+                }
+                let released = Some(Edge {
+                    rising: true,
+                    delta_ms: pending,
+                });
+                if rising {
+                    // the falling edge in between got lost, keep the new one
+                    self.pending = Some(delta_ms);
+                    [released, None]
+                } else {
+                    [released, Some(Edge { rising, delta_ms })]
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn falling(delta_ms: u32) -> Option<Edge> {
+        Some(Edge {
+            rising: false,
+            delta_ms,
+        })
+    }
+
+    fn rising(delta_ms: u32) -> Option<Edge> {
+        Some(Edge {
+            rising: true,
+            delta_ms,
+        })
+    }
+
+    fn stats(edges: u32, merged: u32, short_pulses: u32) -> FilterStats {
+        FilterStats {
+            edges,
+            merged,
+            short_pulses,
+        }
+    }
+
+    #[test]
+    fn clean() {
+        let mut filter = EdgeFilter::new(30);
+        assert_eq!(filter.update(false, 900), [falling(900), None]);
+        // the end of the pulse waits for the next edge
+        assert_eq!(filter.update(true, 100), [None, None]);
+        assert_eq!(filter.update(false, 900), [rising(100), falling(900)]);
+        assert_eq!(filter.stats(), stats(3, 0, 0));
+    }
+
+    #[test]
+    fn spike() {
+        let mut filter = EdgeFilter::new(30);
+        filter.update(false, 900);
+        filter.update(true, 100);
+        // a spike in the pause is passed on, for the decoder to drop it
+        assert_eq!(filter.update(false, 300), [rising(100), falling(300)]);
+        assert_eq!(filter.update(true, 10), [None, None]);
+        assert_eq!(filter.update(false, 590), [rising(10), falling(590)]);
+        assert_eq!(filter.stats(), stats(5, 0, 1));
+    }
+
+    #[test]
+    fn interrupted_pulse() {
+        let mut filter = EdgeFilter::new(30);
+        filter.update(false, 900);
+        // the pulse drops out twice
+        assert_eq!(filter.update(true, 40), [None, None]);
+        assert_eq!(filter.update(false, 5), [None, None]);
+        assert_eq!(filter.update(true, 30), [None, None]);
+        assert_eq!(filter.update(false, 29), [None, None]);
+        assert_eq!(filter.update(true, 96), [None, None]);
+        // and is passed on as a whole
+        assert_eq!(filter.update(false, 800), [rising(200), falling(800)]);
+        assert_eq!(filter.stats(), stats(7, 2, 0));
+    }
+
+    #[test]
+    fn threshold() {
+        let mut filter = EdgeFilter::new(30);
+        filter.update(false, 900);
+        filter.update(true, 100);
+        // an interruption of the threshold ends the pulse
+        assert_eq!(filter.update(false, 30), [rising(100), falling(30)]);
+        // a pulse of the threshold is no short one
+        filter.update(true, 30);
+        assert_eq!(filter.update(false, 840), [rising(30), falling(840)]);
+        assert_eq!(filter.stats(), stats(5, 0, 0));
+
+        filter.update(true, 29);
+        assert_eq!(filter.update(false, 971), [rising(29), falling(971)]);
+        assert_eq!(filter.stats(), stats(7, 0, 1));
+    }
+
+    #[test]
+    fn lost_falling_edge() {
+        let mut filter = EdgeFilter::new(30);
+        filter.update(false, 900);
+        filter.update(true, 100);
+        assert_eq!(filter.update(true, 1000), [rising(100), None]);
+        assert_eq!(filter.update(false, 900), [rising(1000), falling(900)]);
+        assert_eq!(filter.stats(), stats(4, 0, 0));
+    }
+}
@@ -0,0 +1,192 @@
+//! French phrasing, with the minutes following the hour
+//!
+//! The hour is followed by "HEURE" for one and "HEURES" otherwise, except
+//! for noon and midnight which are "MIDI" and "MINUIT" without it. After
+//! those the half hour is "ET DEMI" instead of "ET DEMIE".
+
+use crate::face::{Face, HourName, Phrase, Phrasing};
+use bitflags::bitflags;
+use chrono::NaiveTime;
+
+bitflags! {
+    pub struct MainWord : u32 {
+        const IL_EST = 0x01;
+        const HEURE = 0x02;
+        const HEURES = 0x04;
+        const ET = 0x08;
+        const MOINS = 0x10;
+        const LE = 0x20;
+        const QUART = 0x40;
+        const DEMI = 0x80;
+        const DEMIE = 0x100;
+        const CINQ = 0x200;
+        const DIX = 0x400;
+        const VINGT = 0x800;
+        const MIDI = 0x1000;
+        const MINUIT = 0x2000;
+    }
+}
+
+/// Minutes following the hour, "VINGT CINQ" is lit as "VINGT-CINQ"
+const FIVE_MINUTE_STATE: [Phrase; 12] = [
+    Phrase {
+        main: MainWord::IL_EST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::CINQ.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::DIX.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::ET.bits() | MainWord::QUART.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::VINGT.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::VINGT.bits() | MainWord::CINQ.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::ET.bits() | MainWord::DEMIE.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits()
+            | MainWord::MOINS.bits()
+            | MainWord::VINGT.bits()
+            | MainWord::CINQ.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::MOINS.bits() | MainWord::VINGT.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits()
+            | MainWord::MOINS.bits()
+            | MainWord::LE.bits()
+            | MainWord::QUART.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::MOINS.bits() | MainWord::DIX.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::IL_EST.bits() | MainWord::MOINS.bits() | MainWord::CINQ.bits(),
+        hour_offset: 1,
+    },
+];
+
+const HEURE: HourName = HourName {
+    main: MainWord::HEURE.bits(),
+    number: true,
+    replace: None,
+};
+
+const MINUIT: HourName = HourName {
+    main: MainWord::MINUIT.bits(),
+    number: false,
+    replace: Some((MainWord::DEMIE.bits(), MainWord::DEMI.bits())),
+};
+
+const MIDI: HourName = HourName {
+    main: MainWord::MIDI.bits(),
+    number: false,
+    replace: Some((MainWord::DEMIE.bits(), MainWord::DEMI.bits())),
+};
+
+/// "HEURE" and "HEURES" always follow the hour, there is no word for the
+/// full hour
+const PHRASING: Phrasing = Phrasing {
+    steps: &FIVE_MINUTE_STATE,
+    full_hour: false,
+    hour: HourName {
+        main: MainWord::HEURES.bits(),
+        number: true,
+        replace: None,
+    },
+    hours: &[(0, MINUIT), (1, HEURE), (12, MIDI), (13, HEURE)],
+};
+
+pub fn face(time: NaiveTime) -> Face {
+    PHRASING.face(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::tests::{check_day, phrase, HOUR};
+
+    const HOURS: [&str; 12] = [
+        "douze", "une", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix",
+        "onze",
+    ];
+
+    fn words(face: &Face) -> String {
+        let words = [
+            (MainWord::IL_EST.bits(), "il est"),
+            (HOUR, ""),
+            (MainWord::HEURE.bits(), "heure"),
+            (MainWord::HEURES.bits(), "heures"),
+            (MainWord::MIDI.bits(), "midi"),
+            (MainWord::MINUIT.bits(), "minuit"),
+            (MainWord::MOINS.bits(), "moins"),
+            (MainWord::LE.bits(), "le"),
+            (MainWord::ET.bits(), "et"),
+            (MainWord::QUART.bits(), "quart"),
+            (MainWord::DEMI.bits(), "demi"),
+            (MainWord::DEMIE.bits(), "demie"),
+            (MainWord::DIX.bits(), "dix"),
+            (MainWord::VINGT.bits(), "vingt"),
+            (MainWord::CINQ.bits(), "cinq"),
+        ];
+        // there is no word for the full hour
+        phrase(face, &words, &HOURS, "pile")
+    }
+
+    /// Name of `hour` from 0 to 23
+    fn name(hour: usize) -> String {
+        match hour % 24 {
+            0 => "minuit".into(),
+            12 => "midi".into(),
+            1 | 13 => "une heure".into(),
+            hour => std::format!("{} heures", HOURS[hour % 12]),
+        }
+    }
+
+    #[test]
+    fn five_minute_steps() {
+        let steps = [
+            "il est {}",
+            "il est {} cinq",
+            "il est {} dix",
+            "il est {} et quart",
+            "il est {} vingt",
+            "il est {} vingt cinq",
+            "il est {} et demie",
+            "il est {+} moins vingt cinq",
+            "il est {+} moins vingt",
+            "il est {+} moins le quart",
+            "il est {+} moins dix",
+            "il est {+} moins cinq",
+        ];
+        check_day(face, words, |hour, step| {
+            let phrase = steps[step]
+                .replace("{}", &name(hour))
+                .replace("{+}", &name(hour + 1));
+            // "demi" follows the masculine "midi" and "minuit"
+            match hour {
+                0 | 12 => phrase.replace("demie", "demi"),
+                _ => phrase,
+            }
+        });
+    }
+}
@@ -0,0 +1,441 @@
+use crate::dcf77::{DCF77Frame, DCF77};
+use crate::signal::Pulses;
+use crate::trace::TraceEdge;
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+use core::marker::PhantomData;
+use embedded_hal::digital::v2::OutputPin;
+use heapless::{consts::*, Vec};
+
+/// Flags of a frame which are not derived from the time
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub call_bit: bool,
+    pub dst_announcement: bool,
+    /// summer time is in effect
+    pub summer_time: bool,
+    /// a leap second is inserted at the end of the hour
+    pub leap_second_announcement: bool,
+}
+
+/// Disturbances added to the generated signal
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Impairments {
+    /// maximum deviation of an edge from its nominal time
+    pub jitter_ms: u32,
+    /// probability of a missing pulse, in per mille
+    pub drop_per_mille: u32,
+    /// probability of a pulse encoding the wrong bit, in per mille
+    pub flip_per_mille: u32,
+    /// probability of a short spike in the pause of a second, in per mille
+    pub spike_per_mille: u32,
+}
+
+/// Encode the frame carrying `time`, which is transmitted the minute before
+///
+/// Returns the bits and the length of the frame, which includes a leap second
+/// if one is announced and the frame ends the hour.
+pub fn encode(time: NaiveDateTime, flags: &Flags) -> (u64, usize) {
+    let frame = DCF77Frame {
+        call_bit: flags.call_bit,
+        dst_announcement: flags.dst_announcement,
+        cest: flags.summer_time,
+        cet: !flags.summer_time,
+        leap_second_announcement: flags.leap_second_announcement,
+        minute: time.minute(),
+        hour: time.hour(),
+        day: time.day(),
+        weekday: time.weekday(),
+        month: time.month(),
+        year: time.year(),
+    };
+
+    let len = if flags.leap_second_announcement && time.minute() == 0 {
+        60
+    } else {
+        59
+    };
+    (frame.to_bits(), len)
+}
+
+/// Encoding of a time signal into pulses
+pub trait Encoder {
+    /// Pulses of `second` of the minute starting at `minute`
+    ///
+    /// Returns `None` after the last second of the minute.
+    fn encode_second(minute: NaiveDateTime, flags: &Flags, second: u32) -> Option<Pulses>;
+
+    /// Change the pulses of a second to transmit a wrong bit
+    fn flip(pulses: &mut Pulses);
+
+    /// Offset of the transmitted time to UTC
+    fn utc_offset(flags: &Flags) -> Duration;
+}
+
+impl Encoder for DCF77 {
+    fn encode_second(minute: NaiveDateTime, flags: &Flags, second: u32) -> Option<Pulses> {
+        let (bits, len) = encode(minute + Duration::minutes(1), flags);
+        let mut pulses = Pulses::new();
+        match second as usize {
+            // the second after the last bit is left out as minute marker
+            s if s == len => (),
+            s if s < len => {
+                let width_ms = if (bits >> s) & 1 == 1 { 200 } else { 100 };
+                pulses.push((0, width_ms)).ok();
+            }
+            _ => return None,
+        }
+        Some(pulses)
+    }
+
+    fn flip(pulses: &mut Pulses) {
+        for (_, width_ms) in pulses.iter_mut() {
+            *width_ms = if *width_ms == 100 { 200 } else { 100 };
+        }
+    }
+
+    fn utc_offset(flags: &Flags) -> Duration {
+        Duration::hours(if flags.summer_time { 2 } else { 1 })
+    }
+}
+
+/// Generator of the edges of a time signal
+///
+/// Produces an endless sequence of edges starting with the minute `start`,
+/// timestamps count from the start of that minute. Pulses start with a
+/// falling edge, the first one of a second carries the time of the second in
+/// UTC.
+pub struct Generator<E: Encoder> {
+    /// start of the minute being transmitted
+    minute: NaiveDateTime,
+    flags: Flags,
+    impairments: Impairments,
+    /// state of the pseudo random number generator
+    rng: u32,
+    /// timestamp of the start of the minute
+    minute_ms: u32,
+    second: u32,
+    /// edges of the current second still to be returned, in reverse order
+    pending: Vec<TraceEdge, U8>,
+    encoder: PhantomData<E>,
+}
+
+impl<E: Encoder> Generator<E> {
+    /// `seed` selects the sequence of the random impairments
+    pub fn new(start: NaiveDateTime, flags: Flags, impairments: Impairments, seed: u32) -> Self {
+        Self {
+            minute: start.date().and_hms(start.hour(), start.minute(), 0),
+            flags,
+            impairments,
+            // xorshift gets stuck at zero
+            rng: if seed == 0 { 1 } else { seed },
+            minute_ms: 0,
+            second: 0,
+            pending: Vec::new(),
+            encoder: PhantomData,
+        }
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.flags = flags;
+    }
+
+    pub fn set_impairments(&mut self, impairments: Impairments) {
+        self.impairments = impairments;
+    }
+
+    /// xorshift32, good enough to disturb a signal
+    fn random(&mut self, below: u32) -> u32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng % below
+    }
+
+    fn chance(&mut self, per_mille: u32) -> bool {
+        per_mille > 0 && self.random(1000) < per_mille
+    }
+
+    fn jitter(&mut self, time_ms: u32) -> u32 {
+        let jitter_ms = self.impairments.jitter_ms;
+        if jitter_ms == 0 {
+            return time_ms;
+        }
+        (time_ms + self.random(2 * jitter_ms + 1)).saturating_sub(jitter_ms)
+    }
+
+    /// Generate the edges of the next second
+    fn next_second(&mut self) {
+        let mut pulses = match E::encode_second(self.minute, &self.flags, self.second) {
+            Some(pulses) => pulses,
+            None => {
+                self.minute += Duration::minutes(1);
+                self.minute_ms += self.second * 1000;
+                self.second = 0;
+                E::encode_second(self.minute, &self.flags, 0).unwrap_or_default()
+            }
+        };
+
+        let start_ms = self.minute_ms + self.second * 1000;
+        let mut edges: Vec<TraceEdge, U8> = Vec::new();
+
+        if self.chance(self.impairments.drop_per_mille) {
+            pulses = Pulses::new();
+        }
+        if self.chance(self.impairments.flip_per_mille) {
+            E::flip(&mut pulses);
+        }
+
+        let mut expected = if self.second < 60 {
+            let utc = self.minute - E::utc_offset(&self.flags);
+            Some(utc + Duration::seconds(self.second.into()))
+        } else {
+            None
+        };
+        for (offset_ms, width_ms) in pulses {
+            let falling_ms = self.jitter(start_ms + offset_ms);
+            let rising_ms = self.jitter(start_ms + offset_ms + width_ms).max(falling_ms);
+            edges
+                .push(TraceEdge::new(falling_ms, false, expected.take()))
+                .ok();
+            edges.push(TraceEdge::new(rising_ms, true, None)).ok();
+        }
+
+        if self.chance(self.impairments.spike_per_mille) {
+            // in the pause of the longest pulses still
+            let spike_ms = start_ms + 850 + self.random(100);
+            let width_ms = 5 + self.random(20);
+            edges.push(TraceEdge::new(spike_ms, false, None)).ok();
+            edges
+                .push(TraceEdge::new(spike_ms + width_ms, true, None))
+                .ok();
+        }
+
+        while let Some(edge) = edges.pop() {
+            self.pending.push(edge).ok();
+        }
+        self.second += 1;
+    }
+}
+
+impl<E: Encoder> Iterator for Generator<E> {
+    type Item = TraceEdge;
+
+    fn next(&mut self) -> Option<TraceEdge> {
+        while self.pending.is_empty() {
+            self.next_second();
+        }
+        self.pending.pop()
+    }
+}
+
+/// Output of a generated signal on a pin, e.g. to feed another clock
+pub struct Transmitter<P: OutputPin, E: Encoder> {
+    pin: P,
+    generator: Generator<E>,
+    next: Option<TraceEdge>,
+    time_ms: u32,
+    /// the receiver pulls the output low during a pulse
+    inverted: bool,
+}
+
+impl<P: OutputPin, E: Encoder> Transmitter<P, E> {
+    pub fn new(pin: P, mut generator: Generator<E>, inverted: bool) -> Self {
+        let next = generator.next();
+        Self {
+            pin,
+            generator,
+            next,
+            time_ms: 0,
+            inverted,
+        }
+    }
+
+    /// Advance the signal by `elapsed_ms`, to be called from a periodic timer
+    pub fn tick(&mut self, elapsed_ms: u32) -> Result<(), P::Error> {
+        self.time_ms += elapsed_ms;
+
+        while let Some(edge) = self.next {
+            if edge.time_ms > self.time_ms {
+                break;
+            }
+            if edge.rising ^ self.inverted {
+                self.pin.set_high()?;
+            } else {
+                self.pin.set_low()?;
+            }
+            self.next = self.generator.next();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::classifier::PulseClassifier;
+    use crate::signal::TimeSignal;
+    use crate::trace::{Replay, Report};
+    use chrono::NaiveDate;
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use std::rc::Rc;
+
+    /// Replay the first `minutes` of `generator` through `decoder`
+    pub fn run<E: Encoder, D: TimeSignal>(
+        generator: Generator<E>,
+        decoder: D,
+        minutes: u32,
+    ) -> Report {
+        let mut replay = Replay::new(decoder);
+        for edge in generator.take_while(|edge| edge.time_ms < minutes * 60_000) {
+            replay.edge(&edge).unwrap();
+        }
+        replay.report()
+    }
+
+    /// Check that no wrong time was decoded, and at least `decoded` seconds
+    pub fn check(report: Report, decoded: u32) {
+        assert_eq!(report.mismatches, report.missed, "{:?}", report);
+        assert!(report.decoded >= decoded, "{:?}", report);
+    }
+
+    pub const NOISY: Impairments = Impairments {
+        jitter_ms: 15,
+        drop_per_mille: 5,
+        flip_per_mille: 5,
+        spike_per_mille: 50,
+    };
+
+    /// Noise for the stations without error correction, where a single bad
+    /// bit loses the minute
+    pub const MILD: Impairments = Impairments {
+        jitter_ms: 15,
+        drop_per_mille: 2,
+        flip_per_mille: 2,
+        spike_per_mille: 20,
+    };
+
+    fn dcf77() -> DCF77 {
+        let mut decoder = DCF77::init(PulseClassifier::default(), 2);
+        decoder.set_correction(Some(2));
+        decoder
+    }
+
+    #[test]
+    fn clean() {
+        let start = NaiveDate::from_ymd(2021, 12, 31).and_hms(23, 50, 30);
+        let generator = Generator::<DCF77>::new(start, Flags::default(), Impairments::default(), 1);
+        // every second after the third minute marker
+        check(run(generator, dcf77(), 10), 7 * 59);
+    }
+
+    #[test]
+    fn noisy() {
+        let start = NaiveDate::from_ymd(2021, 12, 31).and_hms(23, 50, 30);
+        for seed in 1..9 {
+            let generator = Generator::<DCF77>::new(start, Flags::default(), NOISY, seed);
+            check(run(generator, dcf77(), 20), 6 * 60);
+        }
+    }
+
+    #[test]
+    fn seed_selects_impairments() {
+        let start = NaiveDate::from_ymd(2021, 6, 1).and_hms(12, 0, 0);
+        let edges = |seed| {
+            Generator::<DCF77>::new(start, Flags::default(), NOISY, seed)
+                .take(200)
+                .collect::<std::vec::Vec<_>>()
+        };
+        assert_eq!(edges(3), edges(3));
+        assert_ne!(edges(3), edges(4));
+    }
+
+    #[test]
+    fn leap_second_frame() {
+        let flags = Flags {
+            leap_second_announcement: true,
+            ..Flags::default()
+        };
+        let (bits, len) = encode(NaiveDate::from_ymd(2017, 1, 1).and_hms(1, 0, 0), &flags);
+        assert_eq!(len, 60);
+        assert!(crate::dcf77::decode(bits, 0, len).is_ok());
+        let (_, len) = encode(NaiveDate::from_ymd(2017, 1, 1).and_hms(1, 1, 0), &flags);
+        assert_eq!(len, 59);
+    }
+
+    #[test]
+    fn leap_minute() {
+        // 2016-12-31 23:59:60 UTC is 2017-01-01 00:59:60 CET
+        let start = NaiveDate::from_ymd(2017, 1, 1).and_hms(0, 50, 0);
+        let flags = Flags {
+            leap_second_announcement: true,
+            ..Flags::default()
+        };
+        let generator = Generator::<DCF77>::new(start, flags, Impairments::default(), 1);
+        let mut replay = Replay::new(dcf77());
+        let minute = NaiveDate::from_ymd(2016, 12, 31).and_hms(23, 59, 0);
+        let last_minute =
+            |time: &NaiveDateTime| time.date() == minute.date() && time.minute() == 59;
+        let (mut seconds, mut leap_second) = (0, false);
+        let (mut start_ms, mut end_ms) = (None, None);
+        for edge in generator.take_while(|edge| edge.time_ms < 14 * 60_000) {
+            let time = replay.edge(&edge).unwrap();
+            if !replay.decoder().second_started() {
+                continue;
+            }
+            if time == Some(minute) {
+                start_ms = Some(edge.time_ms);
+            } else if time == Some(minute + Duration::minutes(1)) {
+                end_ms = Some(edge.time_ms);
+            }
+            if time.filter(last_minute).is_some() {
+                seconds += 1;
+                leap_second |= replay.decoder().leap_second();
+            }
+        }
+
+        // the second without pulse at the end of the minute is the leap second
+        assert_eq!(seconds, 60);
+        assert!(leap_second);
+        assert_eq!(
+            end_ms.zip(start_ms).map(|(end, start)| end - start),
+            Some(61_000)
+        );
+        // the minutes after start in time
+        check(replay.report(), 10 * 60);
+    }
+
+    /// Pin recording the levels set
+    struct Recorder(Rc<RefCell<std::vec::Vec<bool>>>);
+
+    impl OutputPin for Recorder {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(true);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transmitter() {
+        let start = NaiveDate::from_ymd(2021, 6, 1).and_hms(12, 0, 0);
+        let generator = Generator::<DCF77>::new(start, Flags::default(), Impairments::default(), 1);
+        let levels = Rc::new(RefCell::new(std::vec::Vec::new()));
+        let mut transmitter = Transmitter::new(Recorder(levels.clone()), generator, false);
+
+        // second 0 is always a 100 ms pulse
+        transmitter.tick(50).unwrap();
+        assert_eq!(*levels.borrow(), [false]);
+        transmitter.tick(100).unwrap();
+        assert_eq!(*levels.borrow(), [false, true]);
+        transmitter.tick(1000).unwrap();
+        assert_eq!(*levels.borrow(), [false, true, false, true]);
+    }
+}
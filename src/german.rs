@@ -0,0 +1,285 @@
+//! German phrasing, with the regional variants
+
+use crate::face::{Face, HourName, Phrase, Phrasing};
+use bitflags::bitflags;
+use chrono::NaiveTime;
+use core::str::FromStr;
+
+bitflags! {
+    pub struct MainWord : u32 {
+        const ES_IST = 0x01;
+        const FUENF = 0x02;
+        const ZEHN  = 0x04;
+        const ZWANZIG = 0x08;
+        const DREI = 0x10;
+        const VIERTEL = 0x20;
+        const VOR = 0x40;
+        const NACH = 0x80;
+        const HALB = 0x100;
+    }
+}
+
+/// "viertel nach", "zwanzig nach", "zehn nach halb" and "dreiviertel"
+const FIVE_MINUTE_STATE: [Phrase; 12] = [
+    Phrase {
+        main: MainWord::ES_IST.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::FUENF.bits() | MainWord::NACH.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::ZEHN.bits() | MainWord::NACH.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::VIERTEL.bits() | MainWord::NACH.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::ZWANZIG.bits() | MainWord::NACH.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::FUENF.bits() | MainWord::VOR.bits() | MainWord::HALB.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::ES_IST.bits() | MainWord::HALB.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::FUENF.bits() | MainWord::NACH.bits() | MainWord::HALB.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::ZEHN.bits() | MainWord::NACH.bits() | MainWord::HALB.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::ES_IST.bits() | MainWord::DREI.bits() | MainWord::VIERTEL.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::ZEHN.bits() | MainWord::VOR.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::FUENF.bits() | MainWord::VOR.bits(),
+        hour_offset: 1,
+    },
+];
+
+/// `steps` with the phrase of `step` replaced by `phrase`
+const fn with_step(mut steps: [Phrase; 12], step: usize, phrase: Phrase) -> [Phrase; 12] {
+    steps[step] = phrase;
+    steps
+}
+
+/// "zehn vor halb" and "zehn nach halb" for the twenties
+const HALF_STATE: [Phrase; 12] = with_step(
+    FIVE_MINUTE_STATE,
+    4,
+    Phrase {
+        main: MainWord::ZEHN.bits() | MainWord::VOR.bits() | MainWord::HALB.bits(),
+        hour_offset: 1,
+    },
+);
+
+/// "viertel drei" for a quarter past two, as in Swabia and the east
+const SWABIAN_STATE: [Phrase; 12] = with_step(
+    HALF_STATE,
+    3,
+    Phrase {
+        main: MainWord::ES_IST.bits() | MainWord::VIERTEL.bits(),
+        hour_offset: 1,
+    },
+);
+
+const STANDARD: Phrasing = Phrasing {
+    steps: &FIVE_MINUTE_STATE,
+    full_hour: true,
+    hour: HourName::NUMBER,
+    hours: &[],
+};
+
+const HALF: Phrasing = Phrasing {
+    steps: &HALF_STATE,
+    ..STANDARD
+};
+
+const SWABIAN: Phrasing = Phrasing {
+    steps: &SWABIAN_STATE,
+    ..STANDARD
+};
+
+/// Regional phrasing of the quarters and twenties
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// "viertel nach", "zwanzig nach", "zehn nach halb" and "dreiviertel"
+    Standard,
+    /// "zehn vor halb" instead of "zwanzig nach"
+    Half,
+    /// "viertel" and "dreiviertel" of the next hour
+    Swabian,
+}
+
+impl Variant {
+    /// Variant selected by the Schwaben-Schalter
+    pub fn from_switch(on: bool) -> Self {
+        if on {
+            Variant::Swabian
+        } else {
+            Variant::Standard
+        }
+    }
+
+    /// Name of the variant, as accepted by `from_str`
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+            Variant::Half => "half",
+            Variant::Swabian => "swabian",
+        }
+    }
+
+    fn phrasing(self) -> &'static Phrasing {
+        match self {
+            Variant::Standard => &STANDARD,
+            Variant::Half => &HALF,
+            Variant::Swabian => &SWABIAN,
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, ()> {
+        [Variant::Standard, Variant::Half, Variant::Swabian]
+            .iter()
+            .copied()
+            .find(|variant| variant.name() == name)
+            .ok_or(())
+    }
+}
+
+pub fn face(time: NaiveTime, variant: Variant) -> Face {
+    variant.phrasing().face(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::tests::{check_day, fill, phrase, HOUR};
+
+    const HOURS: [&str; 12] = [
+        "zwölf", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun", "zehn",
+        "elf",
+    ];
+    /// Hours followed by "uhr", "ein uhr" instead of "eins uhr"
+    const FULL_HOURS: [&str; 12] = [
+        "zwölf", "ein", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun", "zehn",
+        "elf",
+    ];
+
+    fn words(face: &Face) -> String {
+        let words = [
+            (MainWord::ES_IST.bits(), "es ist"),
+            (MainWord::FUENF.bits(), "fünf"),
+            (MainWord::ZEHN.bits(), "zehn"),
+            (MainWord::ZWANZIG.bits(), "zwanzig"),
+            (MainWord::DREI.bits(), "drei"),
+            (MainWord::VIERTEL.bits(), "viertel"),
+            (MainWord::VOR.bits(), "vor"),
+            (MainWord::NACH.bits(), "nach"),
+            (MainWord::HALB.bits(), "halb"),
+            (HOUR, ""),
+        ];
+        let hours = if face.full_hour { &FULL_HOURS } else { &HOURS };
+        phrase(face, &words, hours, "uhr")
+    }
+
+    fn check(variant: Variant, steps: [&str; 12]) {
+        check_day(
+            |time| face(time, variant),
+            words,
+            |hour, step| match step {
+                0 => fill(steps[step], &FULL_HOURS, hour),
+                _ => fill(steps[step], &HOURS, hour),
+            },
+        );
+    }
+
+    #[test]
+    fn standard() {
+        check(
+            Variant::Standard,
+            [
+                "es ist {} uhr",
+                "fünf nach {}",
+                "zehn nach {}",
+                "viertel nach {}",
+                "zwanzig nach {}",
+                "fünf vor halb {+}",
+                "es ist halb {+}",
+                "fünf nach halb {+}",
+                "zehn nach halb {+}",
+                "es ist drei viertel {+}",
+                "zehn vor {+}",
+                "fünf vor {+}",
+            ],
+        );
+    }
+
+    #[test]
+    fn half() {
+        check(
+            Variant::Half,
+            [
+                "es ist {} uhr",
+                "fünf nach {}",
+                "zehn nach {}",
+                "viertel nach {}",
+                "zehn vor halb {+}",
+                "fünf vor halb {+}",
+                "es ist halb {+}",
+                "fünf nach halb {+}",
+                "zehn nach halb {+}",
+                "es ist drei viertel {+}",
+                "zehn vor {+}",
+                "fünf vor {+}",
+            ],
+        );
+    }
+
+    #[test]
+    fn swabian() {
+        check(
+            Variant::Swabian,
+            [
+                "es ist {} uhr",
+                "fünf nach {}",
+                "zehn nach {}",
+                "es ist viertel {+}",
+                "zehn vor halb {+}",
+                "fünf vor halb {+}",
+                "es ist halb {+}",
+                "fünf nach halb {+}",
+                "zehn nach halb {+}",
+                "es ist drei viertel {+}",
+                "zehn vor {+}",
+                "fünf vor {+}",
+            ],
+        );
+    }
+
+    #[test]
+    fn names() {
+        for &variant in [Variant::Standard, Variant::Half, Variant::Swabian].iter() {
+            assert_eq!(variant.name().parse(), Ok(variant));
+        }
+        assert_eq!("schwäbisch".parse::<Variant>(), Err(()));
+    }
+}
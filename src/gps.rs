@@ -0,0 +1,172 @@
+use crate::confidence::Confidence;
+use crate::nmea::{Error, NmeaParser, Sentence};
+use chrono::{Duration, NaiveDateTime, Timelike};
+use nb::Error::{Other, WouldBlock};
+
+/// Seconds without a pulse after which the sentences are used directly
+const PULSE_TIMEOUT_S: i64 = 3;
+/// Typical time from the start of a second to the end of its sentence
+///
+/// Receivers start sending some 100ms into the second, and an RMC sentence
+/// takes about 80ms at 9600 baud.
+pub const SENTENCE_DELAY_MS: i64 = 200;
+
+/// Time from a GPS receiver, optionally aligned to its PPS output
+///
+/// The sentences following a pulse describe the second started by it, so the
+/// next pulse starts the one after. Without pulses the time is taken from the
+/// sentences as they arrive, only about `SENTENCE_DELAY_MS` after the start
+/// of the second.
+///
+/// A pulse is only trusted if a RMC sentence with a valid fix described the
+/// second before it. Receivers keep pulsing and sending ZDA sentences from
+/// their own clock after losing the fix.
+pub struct GpsTime {
+    parser: NmeaParser,
+    /// time of the last sentence since the last pulse
+    pending: Option<NaiveDateTime>,
+    /// time of the last RMC sentence with a valid fix
+    fix: Option<NaiveDateTime>,
+    /// time of the second started by the last pulse with a known time
+    last_pulse: Option<NaiveDateTime>,
+}
+
+impl Default for GpsTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpsTime {
+    pub fn new() -> Self {
+        Self {
+            parser: NmeaParser::new(),
+            pending: None,
+            fix: None,
+            last_pulse: None,
+        }
+    }
+
+    /// Add a byte received from the GPS
+    ///
+    /// Returns the time of a completed sentence if there are no pulses to
+    /// align it to.
+    pub fn byte(&mut self, byte: u8) -> nb::Result<NaiveDateTime, Error> {
+        let (sentence, time) = match self.parser.push(byte) {
+            Err(Other(Error::NoFix)) => {
+                self.fix = None;
+                return Err(Other(Error::NoFix));
+            }
+            result => result?,
+        };
+        // sentences sent more than once per second
+        if time.nanosecond() % 1_000_000_000 != 0 {
+            return Err(WouldBlock);
+        }
+
+        if sentence == Sentence::Rmc {
+            self.fix = Some(time);
+        }
+        self.pending = Some(time);
+        match self.last_pulse {
+            Some(pulse) if (time - pulse).num_seconds().abs() <= PULSE_TIMEOUT_S => Err(WouldBlock),
+            _ => Ok(time),
+        }
+    }
+
+    /// A pulse started a second
+    ///
+    /// Returns the time of the second if a sentence has been received since
+    /// the previous pulse. It is confirmed if that sentence was a RMC with a
+    /// valid fix, otherwise only tentative.
+    pub fn pulse(&mut self) -> Option<(NaiveDateTime, Confidence)> {
+        let pending = self.pending.take()?;
+        let confidence = if self.fix == Some(pending) {
+            Confidence::Confirmed
+        } else {
+            Confidence::Tentative(1)
+        };
+        let time = pending + Duration::seconds(1);
+        self.last_pulse = Some(time);
+        Some((time, confidence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Times of the sentences completed by `sentence`
+    fn send(gps: &mut GpsTime, sentence: &str) -> std::vec::Vec<nb::Result<NaiveDateTime, Error>> {
+        sentence
+            .bytes()
+            .chain("\r\n".bytes())
+            .map(|byte| gps.byte(byte))
+            .filter(|result| *result != Err(WouldBlock))
+            .collect()
+    }
+
+    fn zda(second: u32) -> std::string::String {
+        let data = std::format!("GPZDA,1200{:02}.00,16,09,2002,00,00", second);
+        let checksum = data.bytes().fold(0, |sum, byte| sum ^ byte);
+        std::format!("${}*{:02X}", data, checksum)
+    }
+
+    fn rmc(second: u32, status: char) -> std::string::String {
+        let data = std::format!("GPRMC,1200{:02}.00,{},,,,,,,160902,,,A", second, status);
+        let checksum = data.bytes().fold(0, |sum, byte| sum ^ byte);
+        std::format!("${}*{:02X}", data, checksum)
+    }
+
+    fn time(second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2002, 9, 16).and_hms(12, 0, second)
+    }
+
+    #[test]
+    fn without_pulses() {
+        let mut gps = GpsTime::new();
+        assert_eq!(send(&mut gps, &zda(1)), [Ok(time(1))]);
+        assert_eq!(send(&mut gps, &zda(2)), [Ok(time(2))]);
+        // sentences within the second
+        assert_eq!(send(&mut gps, "$GPZDA,120002.50,16,09,2002,00,00*6C"), []);
+    }
+
+    #[test]
+    fn aligned_to_pulses() {
+        let mut gps = GpsTime::new();
+        assert_eq!(gps.pulse(), None);
+        assert_eq!(send(&mut gps, &zda(1)), [Ok(time(1))]);
+        // the sentence described the second before the pulse
+        assert_eq!(gps.pulse(), Some((time(2), Confidence::Tentative(1))));
+        assert_eq!(send(&mut gps, &zda(2)), []);
+        assert_eq!(gps.pulse(), Some((time(3), Confidence::Tentative(1))));
+        // a pulse without sentence
+        assert_eq!(gps.pulse(), None);
+
+        // the sentences are taken again once the pulses are gone
+        assert_eq!(send(&mut gps, &zda(5)), []);
+        assert_eq!(send(&mut gps, &zda(6)), []);
+        assert_eq!(send(&mut gps, &zda(7)), [Ok(time(7))]);
+    }
+
+    #[test]
+    fn confirmed_by_fix() {
+        let mut gps = GpsTime::new();
+        send(&mut gps, &rmc(1, 'A'));
+        assert_eq!(gps.pulse(), Some((time(2), Confidence::Confirmed)));
+        // the fix has to be from the second before the pulse
+        send(&mut gps, &zda(2));
+        assert_eq!(gps.pulse(), Some((time(3), Confidence::Tentative(1))));
+        send(&mut gps, &rmc(3, 'A'));
+        send(&mut gps, &zda(3));
+        assert_eq!(gps.pulse(), Some((time(4), Confidence::Confirmed)));
+
+        // lost the fix
+        assert_eq!(send(&mut gps, &rmc(4, 'V')), [Err(Other(Error::NoFix))]);
+        send(&mut gps, &zda(4));
+        assert_eq!(gps.pulse(), Some((time(5), Confidence::Tentative(1))));
+        send(&mut gps, &rmc(5, 'A'));
+        assert_eq!(gps.pulse(), Some((time(6), Confidence::Confirmed)));
+    }
+}
@@ -0,0 +1,281 @@
+//! JJY, transmitted from Ohtakadoya-yama on 40kHz and Hagane-yama on 60kHz
+//!
+//! The carrier is at full power at the start of every second for 800ms for a
+//! zero, 500ms for a one and 200ms for a marker, so the receiver has to output
+//! the full carrier as pulse, the opposite polarity of DCF77. Two consecutive
+//! markers start a minute. The time is JST and refers to the minute it is
+//! transmitted in.
+//!
+//! At minutes 15 and 45 the call sign is keyed in Morse code in place of the
+//! year, and service notices replace the weekday. The date of these minutes
+//! is completed from the frame of the minute before.
+
+use crate::dcf77::{infer_century, DCF77Frame, Error};
+use crate::generator::{Encoder, Flags};
+use crate::signal::{
+    insert_weighted, marker_bits, matches, weighted, Decoder, Field, Pulses, Station, Symbol,
+};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use heapless::{consts::*, Vec};
+
+const TOLERANCE_MS: u32 = 60;
+/// Seconds transmitting a marker
+const MARKERS: [usize; 7] = [0, 9, 19, 29, 39, 49, 59];
+
+const MINUTE: Field = (1, &[40, 20, 10, 0, 8, 4, 2, 1]);
+const HOUR: Field = (12, &[20, 10, 0, 8, 4, 2, 1]);
+const DAY_OF_YEAR: Field = (22, &[200, 100, 0, 80, 40, 20, 10, 0, 8, 4, 2, 1]);
+const YEAR: Field = (41, &[80, 40, 20, 10, 8, 4, 2, 1]);
+const WEEKDAY: Field = (50, &[4, 2, 1]);
+/// Seconds keying the call sign in minutes 15 and 45
+const CALL_SIGN: core::ops::RangeInclusive<usize> = 40..=48;
+/// Bits covered by the even parity bit, with the error reported
+const PARITIES: [(usize, usize, usize, Error); 2] = [
+    (12, 18, 36, Error::ParityErrorHour),
+    (1, 8, 37, Error::ParityErrorMinute),
+];
+
+pub struct Jjy;
+
+pub type JJY = Decoder<Jjy>;
+
+/// Even parity of the bits `first` to `last`
+fn parity(bits: u64, first: usize, last: usize) -> bool {
+    (first..=last).filter(|bit| (bits >> bit) & 1 == 1).count() % 2 == 1
+}
+
+/// Whether the call sign is transmitted in `minute`
+fn call_sign(minute: u32) -> bool {
+    minute == 15 || minute == 45
+}
+
+impl Station for Jjy {
+    type Symbol = Symbol;
+
+    fn symbol(pulses: &[(u32, u32)]) -> Option<Symbol> {
+        match *pulses {
+            [(0, width)] if matches(width, 800, TOLERANCE_MS) => Some(Symbol::Zero),
+            [(0, width)] if matches(width, 500, TOLERANCE_MS) => Some(Symbol::One),
+            [(0, width)] if matches(width, 200, TOLERANCE_MS) => Some(Symbol::Marker),
+            _ => None,
+        }
+    }
+
+    fn minute_start(previous: Option<Symbol>, symbol: Option<Symbol>) -> bool {
+        previous == Some(Symbol::Marker) && symbol == Some(Symbol::Marker)
+    }
+
+    fn decode(
+        symbols: &[Option<Symbol>],
+        previous: Option<&DCF77Frame>,
+    ) -> Result<DCF77Frame, Error> {
+        // the call sign is no valid symbol, whether it is expected depends on
+        // the minute
+        let mut without_call_sign: Vec<Option<Symbol>, U64> =
+            Vec::from_slice(symbols).map_err(|_| Error::ProtocolError)?;
+        for second in CALL_SIGN {
+            if let Some(symbol) = without_call_sign.get_mut(second) {
+                *symbol = Some(Symbol::Zero);
+            }
+        }
+        let mut bits = marker_bits(&without_call_sign, &MARKERS)?;
+        let call_sign = call_sign(weighted(bits, MINUTE)?);
+        if !call_sign {
+            bits = marker_bits(symbols, &MARKERS)?;
+        }
+        for &(first, last, bit, error) in PARITIES.iter() {
+            if parity(bits, first, last) != ((bits >> bit) & 1 == 1) {
+                return Err(error);
+            }
+        }
+
+        let field = |field| weighted(bits, field);
+        let day_of_year = field(DAY_OF_YEAR)?;
+        let (date, leap_second_announcement) = match (call_sign, previous) {
+            // the year can't change within the hour
+            (true, Some(previous)) => (
+                NaiveDate::from_yo_opt(previous.year, day_of_year).ok_or(Error::InvalidDate)?,
+                previous.leap_second_announcement,
+            ),
+            (true, None) => return Err(Error::InvalidDate),
+            (false, _) => {
+                let weekday = match field(WEEKDAY)? {
+                    0 => Weekday::Sun,
+                    1 => Weekday::Mon,
+                    2 => Weekday::Tue,
+                    3 => Weekday::Wed,
+                    4 => Weekday::Thu,
+                    5 => Weekday::Fri,
+                    6 => Weekday::Sat,
+                    _ => return Err(Error::InvalidDate),
+                };
+                let date = infer_century(field(YEAR)?, weekday, |year| {
+                    NaiveDate::from_yo_opt(year, day_of_year)
+                })?;
+                (date, (bits >> 53) & 1 == 1)
+            }
+        };
+
+        let frame = DCF77Frame {
+            call_bit: false,
+            dst_announcement: false,
+            cest: false,
+            cet: false,
+            leap_second_announcement,
+            minute: field(MINUTE)?,
+            hour: field(HOUR)?,
+            day: date.day(),
+            weekday: date.weekday(),
+            month: date.month(),
+            year: date.year(),
+        };
+        // transmitted is the start of the minute just completed
+        frame.next_minute()
+    }
+
+    fn utc_offset(_frame: &DCF77Frame) -> Duration {
+        Duration::hours(9)
+    }
+}
+
+/// Bits of the minute starting at `time`, markers excluded
+pub fn encode(time: NaiveDateTime, flags: &Flags) -> u64 {
+    let mut bits = 0u64;
+    insert_weighted(&mut bits, MINUTE, time.minute());
+    insert_weighted(&mut bits, HOUR, time.hour());
+    insert_weighted(&mut bits, DAY_OF_YEAR, time.ordinal());
+    for &(first, last, bit, _) in PARITIES.iter() {
+        bits |= (parity(bits, first, last) as u64) << bit;
+    }
+
+    // no service notices are sent with the call sign
+    if !call_sign(time.minute()) {
+        insert_weighted(&mut bits, YEAR, (time.year() % 100) as u32);
+        insert_weighted(&mut bits, WEEKDAY, time.weekday().num_days_from_sunday());
+        bits |= (flags.leap_second_announcement as u64) << 53;
+    }
+    bits
+}
+
+impl Encoder for Jjy {
+    fn encode_second(minute: NaiveDateTime, flags: &Flags, second: u32) -> Option<Pulses> {
+        if second >= 60 {
+            return None;
+        }
+
+        let mut pulses = Pulses::new();
+        if call_sign(minute.minute()) && CALL_SIGN.contains(&(second as usize)) {
+            // dits and dahs of the Morse code
+            pulses.push((0, 100)).ok();
+            pulses.push((300, 300)).ok();
+            return Some(pulses);
+        }
+
+        let width_ms = if MARKERS.contains(&(second as usize)) {
+            200
+        } else if (encode(minute, flags) >> second) & 1 == 1 {
+            500
+        } else {
+            800
+        };
+        pulses.push((0, width_ms)).ok();
+        Some(pulses)
+    }
+
+    fn flip(pulses: &mut Pulses) {
+        for (_, width_ms) in pulses.iter_mut() {
+            *width_ms = match *width_ms {
+                500 => 800,
+                800 => 500,
+                width_ms => width_ms,
+            };
+        }
+    }
+
+    fn utc_offset(_flags: &Flags) -> Duration {
+        Duration::hours(9)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::confidence::Confidence;
+    use crate::generator::tests::{check, run, MILD};
+    use crate::generator::{Generator, Impairments};
+    use crate::signal::TimeSignal;
+    use crate::trace::Replay;
+
+    fn time(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2024, 2, 28).and_hms(hour, minute, 0)
+    }
+
+    /// Symbols of the minute starting at `time`, as transmitted
+    fn symbols(time: NaiveDateTime) -> std::vec::Vec<Option<Symbol>> {
+        (0..60)
+            .map(|second| Jjy::symbol(&Jjy::encode_second(time, &Flags::default(), second)?))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let generator =
+            Generator::<Jjy>::new(time(8, 5), Flags::default(), Impairments::default(), 1);
+        // every second from the end of the third minute marker on
+        check(run(generator, JJY::init(2), 10), 7 * 60 - 1);
+    }
+
+    #[test]
+    fn noisy() {
+        for seed in 1..5 {
+            let generator = Generator::<Jjy>::new(time(8, 5), Flags::default(), MILD, seed);
+            check(run(generator, JJY::init(2), 20), 14 * 60);
+        }
+    }
+
+    #[test]
+    fn call_sign_keeps_confirmation() {
+        let generator =
+            Generator::<Jjy>::new(time(8, 10), Flags::default(), Impairments::default(), 1);
+        let mut replay = Replay::new(JJY::init(2));
+        for edge in generator.take_while(|edge| edge.time_ms < 40 * 60_000) {
+            replay.edge(&edge).unwrap();
+            if edge.time_ms > 3 * 60_000 + 1000 {
+                assert_eq!(replay.decoder().confidence(), Confidence::Confirmed);
+            }
+        }
+        let frame = replay.decoder().frame().unwrap();
+        assert_eq!(frame.datetime(0), Ok(time(8, 49)));
+    }
+
+    #[test]
+    fn frame() {
+        let frame = Jjy::decode(&symbols(time(13, 37)), None).unwrap();
+        assert_eq!(frame.datetime(0), Ok(time(13, 38)));
+        assert_eq!(frame.weekday, Weekday::Wed);
+        assert_eq!(<Jjy as Station>::utc_offset(&frame), Duration::hours(9));
+
+        let mut unreadable = symbols(time(13, 37));
+        unreadable[CALL_SIGN.start() + 1] = None;
+        assert_eq!(Jjy::decode(&unreadable, None), Err(Error::UnreadableBit));
+
+        let mut parity = symbols(time(13, 37));
+        parity[1] = Some(Symbol::One);
+        assert_eq!(Jjy::decode(&parity, None), Err(Error::ParityErrorMinute));
+    }
+
+    #[test]
+    fn call_sign_minutes() {
+        for &(minute, next) in [(15, 16), (45, 46)].iter() {
+            let symbols = symbols(time(13, minute));
+            assert!(symbols[CALL_SIGN].iter().all(Option::is_none));
+
+            // the date is taken from the minute before
+            let previous = Jjy::decode(&self::symbols(time(13, minute - 1)), None).unwrap();
+            let frame = Jjy::decode(&symbols, Some(&previous)).unwrap();
+            assert_eq!(frame.datetime(0), Ok(time(13, next)));
+            assert_eq!(frame.weekday, Weekday::Wed);
+            assert_eq!(Jjy::decode(&symbols, None), Err(Error::InvalidDate));
+        }
+    }
+}
@@ -0,0 +1,707 @@
+//! Description of the words on a clock face and how they are driven
+//!
+//! Every word has its own enable pin and is powered through one or more of
+//! the driver lines. The words lit for a time are given as bit mask, with
+//! bit `n` for the `n`th word of the layout.
+
+use crate::face::{Face, Language};
+use crate::{dutch, english, french, german, spanish};
+use bitflags::bitflags;
+
+bitflags! {
+    pub struct DriverLine : u8 {
+        const LINE1A = 0x01;
+        const LINE1B = 0x02;
+        const LINE2A = 0x04;
+        const LINE2B = 0x08;
+        const LINE3 = 0x10;
+        const LINE4 = 0x20;
+        const LINE5A = 0x40;
+        const LINE5B = 0x80;
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Word {
+    /// index of the enable pin passed to the display
+    pub enable: usize,
+    pub lines: DriverLine,
+}
+
+pub struct Layout {
+    pub language: Language,
+    pub words: &'static [Word],
+    /// words lit for each of the main words of the language, given as bits
+    pub main: &'static [(u32, u32)],
+    /// words lit for the hours, starting with twelve
+    pub hours: [u32; 12],
+    /// words lit for the full hours including "UHR", like "EIN UHR"
+    pub full_hours: [u32; 12],
+    /// words lit instead of "ZWÖLF UHR" at midnight, like "MITTERNACHT"
+    pub midnight: Option<u32>,
+}
+
+const fn driver_lines(lines: u8) -> DriverLine {
+    DriverLine::from_bits_truncate(lines)
+}
+
+/// Words of board revision 1, named as on the German face
+const REV1_WORDS: &[Word] = &[
+    // ES IST
+    Word {
+        enable: 0,
+        lines: driver_lines(DriverLine::LINE1A.bits() | DriverLine::LINE1B.bits()),
+    },
+    // UHR
+    Word {
+        enable: 1,
+        lines: DriverLine::LINE4,
+    },
+    // HALB
+    Word {
+        enable: 2,
+        lines: DriverLine::LINE3,
+    },
+    // VOR
+    Word {
+        enable: 3,
+        lines: DriverLine::LINE4,
+    },
+    // DREI
+    Word {
+        enable: 4,
+        lines: DriverLine::LINE3,
+    },
+    // VIERTEL
+    Word {
+        enable: 5,
+        lines: driver_lines(DriverLine::LINE2A.bits() | DriverLine::LINE2B.bits()),
+    },
+    // NACH
+    Word {
+        enable: 6,
+        lines: DriverLine::LINE4,
+    },
+    // ZEHN
+    Word {
+        enable: 7,
+        lines: DriverLine::LINE2A,
+    },
+    // ZWANZIG
+    Word {
+        enable: 8,
+        lines: driver_lines(DriverLine::LINE2A.bits() | DriverLine::LINE2B.bits()),
+    },
+    // FÜNF
+    Word {
+        enable: 9,
+        lines: DriverLine::LINE2A,
+    },
+    // EINS to ZWÖLF
+    Word {
+        enable: 10,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 11,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 12,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 13,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 14,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 15,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    Word {
+        enable: 16,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    Word {
+        enable: 17,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 18,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 19,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 20,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 21,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+];
+
+const REV1_HOURS: [u32; 12] = [
+    1 << 21,
+    1 << 10,
+    1 << 11,
+    1 << 12,
+    1 << 13,
+    1 << 14,
+    1 << 15,
+    1 << 16,
+    1 << 17,
+    1 << 18,
+    1 << 19,
+    1 << 20,
+];
+
+/// Hours followed by the word in the place of "UHR"
+///
+/// Revision 1 has the S of "EINS" on the same enable pin, so the German face
+/// shows "EINS UHR" at one o'clock. The S is left dark on faces having it.
+const REV1_FULL_HOURS: [u32; 12] = [
+    1 << 21 | 1 << 1,
+    1 << 10 | 1 << 1,
+    1 << 11 | 1 << 1,
+    1 << 12 | 1 << 1,
+    1 << 13 | 1 << 1,
+    1 << 14 | 1 << 1,
+    1 << 15 | 1 << 1,
+    1 << 16 | 1 << 1,
+    1 << 17 | 1 << 1,
+    1 << 18 | 1 << 1,
+    1 << 19 | 1 << 1,
+    1 << 20 | 1 << 1,
+];
+
+/// Board revision 1 with the German face
+pub const DEFAULT: Layout = Layout {
+    language: Language::German,
+    words: REV1_WORDS,
+    main: &[
+        (german::MainWord::ES_IST.bits(), 1 << 0),
+        (german::MainWord::HALB.bits(), 1 << 2),
+        (german::MainWord::VOR.bits(), 1 << 3),
+        (german::MainWord::DREI.bits(), 1 << 4),
+        (german::MainWord::VIERTEL.bits(), 1 << 5),
+        (german::MainWord::NACH.bits(), 1 << 6),
+        (german::MainWord::ZEHN.bits(), 1 << 7),
+        (german::MainWord::ZWANZIG.bits(), 1 << 8),
+        (german::MainWord::FUENF.bits(), 1 << 9),
+    ],
+    hours: REV1_HOURS,
+    full_hours: REV1_FULL_HOURS,
+    midnight: None,
+};
+
+/// Words of board revision 1 followed by two on the spare enable pins
+///
+/// The S of EINS is wired on its own, so one o'clock can be shown as
+/// "EIN UHR".
+const GERMAN_EXTENDED_WORDS: &[Word] = &[
+    // ES IST
+    Word {
+        enable: 0,
+        lines: driver_lines(DriverLine::LINE1A.bits() | DriverLine::LINE1B.bits()),
+    },
+    // UHR
+    Word {
+        enable: 1,
+        lines: DriverLine::LINE4,
+    },
+    // HALB
+    Word {
+        enable: 2,
+        lines: DriverLine::LINE3,
+    },
+    // VOR
+    Word {
+        enable: 3,
+        lines: DriverLine::LINE4,
+    },
+    // DREI
+    Word {
+        enable: 4,
+        lines: DriverLine::LINE3,
+    },
+    // VIERTEL
+    Word {
+        enable: 5,
+        lines: driver_lines(DriverLine::LINE2A.bits() | DriverLine::LINE2B.bits()),
+    },
+    // NACH
+    Word {
+        enable: 6,
+        lines: DriverLine::LINE4,
+    },
+    // ZEHN
+    Word {
+        enable: 7,
+        lines: DriverLine::LINE2A,
+    },
+    // ZWANZIG
+    Word {
+        enable: 8,
+        lines: driver_lines(DriverLine::LINE2A.bits() | DriverLine::LINE2B.bits()),
+    },
+    // FÜNF
+    Word {
+        enable: 9,
+        lines: DriverLine::LINE2A,
+    },
+    // EIN, ZWEI to ZWÖLF
+    Word {
+        enable: 10,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 11,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 12,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 13,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 14,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 15,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    Word {
+        enable: 16,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    Word {
+        enable: 17,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 18,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 19,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 20,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 21,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    // S of EINS
+    Word {
+        enable: 22,
+        lines: DriverLine::LINE5A,
+    },
+    // MITTERNACHT
+    Word {
+        enable: 23,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+];
+
+/// Hours of the German face with the spare enable pins, "EINS" lights the S
+const GERMAN_EXTENDED_HOURS: [u32; 12] = [
+    1 << 21,
+    1 << 10 | 1 << 22,
+    1 << 11,
+    1 << 12,
+    1 << 13,
+    1 << 14,
+    1 << 15,
+    1 << 16,
+    1 << 17,
+    1 << 18,
+    1 << 19,
+    1 << 20,
+];
+
+/// German face with the spare enable pins
+///
+/// Shows "EIN UHR" at one o'clock and "MITTERNACHT" at midnight.
+pub const GERMAN_EXTENDED: Layout = Layout {
+    language: Language::German,
+    words: GERMAN_EXTENDED_WORDS,
+    main: DEFAULT.main,
+    hours: GERMAN_EXTENDED_HOURS,
+    full_hours: REV1_FULL_HOURS,
+    midnight: Some(1 << 23),
+};
+
+/// Board revision 1 with an English face
+///
+/// The words take the places of German ones of about the same length, "IT IS"
+/// that of "ES IST" and "O'CLOCK" that of "UHR".
+pub const ENGLISH: Layout = Layout {
+    language: Language::English,
+    words: REV1_WORDS,
+    main: &[
+        (english::MainWord::IT_IS.bits(), 1 << 0),
+        (english::MainWord::HALF.bits(), 1 << 2),
+        (english::MainWord::TO.bits(), 1 << 3),
+        (english::MainWord::PAST.bits(), 1 << 4),
+        (english::MainWord::QUARTER.bits(), 1 << 5),
+        (english::MainWord::TEN.bits(), 1 << 7),
+        (english::MainWord::TWENTY.bits(), 1 << 8),
+        (english::MainWord::FIVE.bits(), 1 << 9),
+    ],
+    hours: REV1_HOURS,
+    full_hours: REV1_FULL_HOURS,
+    midnight: None,
+};
+
+/// Board revision 1 with a Dutch face
+///
+/// "UUR" takes the place of "UHR", the places of "DREI" and "ZWANZIG" stay
+/// dark.
+pub const DUTCH: Layout = Layout {
+    language: Language::Dutch,
+    words: REV1_WORDS,
+    main: &[
+        (dutch::MainWord::HET_IS.bits(), 1 << 0),
+        (dutch::MainWord::HALF.bits(), 1 << 2),
+        (dutch::MainWord::VOOR.bits(), 1 << 3),
+        (dutch::MainWord::KWART.bits(), 1 << 5),
+        (dutch::MainWord::OVER.bits(), 1 << 6),
+        (dutch::MainWord::TIEN.bits(), 1 << 7),
+        (dutch::MainWord::VIJF.bits(), 1 << 9),
+    ],
+    hours: REV1_HOURS,
+    full_hours: REV1_FULL_HOURS,
+    midnight: None,
+};
+
+/// Board revision 1 with a Spanish face
+///
+/// "ES LA" and "SON LAS" are lit as one word each, "Y" takes the place of
+/// "UHR" as there is no word for the full hour.
+pub const SPANISH: Layout = Layout {
+    language: Language::Spanish,
+    words: REV1_WORDS,
+    main: &[
+        (spanish::MainWord::ES.bits(), 1 << 0),
+        (spanish::MainWord::LA.bits(), 1 << 0),
+        (spanish::MainWord::Y.bits(), 1 << 1),
+        (spanish::MainWord::MEDIA.bits(), 1 << 2),
+        (spanish::MainWord::MENOS.bits(), 1 << 3),
+        (spanish::MainWord::DIEZ.bits(), 1 << 4),
+        (spanish::MainWord::CUARTO.bits(), 1 << 5),
+        (spanish::MainWord::SON.bits(), 1 << 6),
+        (spanish::MainWord::LAS.bits(), 1 << 6),
+        (spanish::MainWord::CINCO.bits(), 1 << 7),
+        (spanish::MainWord::VEINTICINCO.bits(), 1 << 8),
+        (spanish::MainWord::VEINTE.bits(), 1 << 9),
+    ],
+    hours: REV1_HOURS,
+    full_hours: REV1_HOURS,
+    midnight: None,
+};
+
+/// Words of board revision 1 followed by three on the spare enable pins
+///
+/// The French face has more words than fit on the 22 of revision 1.
+const FRENCH_WORDS: &[Word] = &[
+    // IL EST
+    Word {
+        enable: 0,
+        lines: driver_lines(DriverLine::LINE1A.bits() | DriverLine::LINE1B.bits()),
+    },
+    // HEURE
+    Word {
+        enable: 1,
+        lines: DriverLine::LINE4,
+    },
+    // DEMI
+    Word {
+        enable: 2,
+        lines: DriverLine::LINE3,
+    },
+    // ET
+    Word {
+        enable: 3,
+        lines: DriverLine::LINE4,
+    },
+    // LE
+    Word {
+        enable: 4,
+        lines: DriverLine::LINE3,
+    },
+    // QUART
+    Word {
+        enable: 5,
+        lines: driver_lines(DriverLine::LINE2A.bits() | DriverLine::LINE2B.bits()),
+    },
+    // MOINS
+    Word {
+        enable: 6,
+        lines: DriverLine::LINE4,
+    },
+    // DIX
+    Word {
+        enable: 7,
+        lines: DriverLine::LINE2A,
+    },
+    // VINGT
+    Word {
+        enable: 8,
+        lines: driver_lines(DriverLine::LINE2A.bits() | DriverLine::LINE2B.bits()),
+    },
+    // CINQ
+    Word {
+        enable: 9,
+        lines: DriverLine::LINE2A,
+    },
+    // UNE to ONZE
+    Word {
+        enable: 10,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 11,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 12,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 13,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 14,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 15,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    Word {
+        enable: 16,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    Word {
+        enable: 17,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 18,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 19,
+        lines: DriverLine::LINE5A,
+    },
+    Word {
+        enable: 20,
+        lines: DriverLine::LINE5A,
+    },
+    // MIDI
+    Word {
+        enable: 21,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    // MINUIT
+    Word {
+        enable: 22,
+        lines: driver_lines(DriverLine::LINE5A.bits() | DriverLine::LINE5B.bits()),
+    },
+    // S of HEURES
+    Word {
+        enable: 23,
+        lines: DriverLine::LINE4,
+    },
+    // E of DEMIE
+    Word {
+        enable: 24,
+        lines: DriverLine::LINE3,
+    },
+];
+
+/// Hours of the French face, twelve is always named "MIDI" or "MINUIT"
+const FRENCH_HOURS: [u32; 12] = [
+    0,
+    1 << 10,
+    1 << 11,
+    1 << 12,
+    1 << 13,
+    1 << 14,
+    1 << 15,
+    1 << 16,
+    1 << 17,
+    1 << 18,
+    1 << 19,
+    1 << 20,
+];
+
+/// French face with the spare enable pins
+///
+/// "HEURES" and "DEMIE" light the "S" and "E" next to "HEURE" and "DEMI".
+pub const FRENCH: Layout = Layout {
+    language: Language::French,
+    words: FRENCH_WORDS,
+    main: &[
+        (french::MainWord::IL_EST.bits(), 1 << 0),
+        (french::MainWord::HEURE.bits(), 1 << 1),
+        (french::MainWord::HEURES.bits(), 1 << 1 | 1 << 23),
+        (french::MainWord::DEMI.bits(), 1 << 2),
+        (french::MainWord::DEMIE.bits(), 1 << 2 | 1 << 24),
+        (french::MainWord::ET.bits(), 1 << 3),
+        (french::MainWord::LE.bits(), 1 << 4),
+        (french::MainWord::QUART.bits(), 1 << 5),
+        (french::MainWord::MOINS.bits(), 1 << 6),
+        (french::MainWord::DIX.bits(), 1 << 7),
+        (french::MainWord::VINGT.bits(), 1 << 8),
+        (french::MainWord::CINQ.bits(), 1 << 9),
+        (french::MainWord::MIDI.bits(), 1 << 21),
+        (french::MainWord::MINUIT.bits(), 1 << 22),
+    ],
+    hours: FRENCH_HOURS,
+    full_hours: FRENCH_HOURS,
+    midnight: None,
+};
+
+impl Layout {
+    /// Words to light for `face`
+    pub fn lit(&self, face: &Face) -> u32 {
+        let hour = match (face.hour, face.full_hour, self.midnight) {
+            (None, _, _) => 0,
+            (Some(0), true, Some(midnight)) => midnight,
+            (Some(hour), true, _) => self.full_hours[hour % 12],
+            (Some(hour), false, _) => self.hours[hour % 12],
+        };
+
+        self.main
+            .iter()
+            .filter(|(word, _)| face.main & word != 0)
+            .fold(hour, |lit, (_, words)| lit | words)
+    }
+
+    /// Driver lines powering the words in `lit`
+    pub fn lines(&self, lit: u32) -> DriverLine {
+        self.words
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| lit & (1 << i) != 0)
+            .fold(DriverLine::empty(), |lines, (_, word)| lines | word.lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+
+    const LAYOUTS: [&Layout; 6] = [
+        &DEFAULT,
+        &GERMAN_EXTENDED,
+        &ENGLISH,
+        &DUTCH,
+        &FRENCH,
+        &SPANISH,
+    ];
+
+    #[test]
+    fn every_word_is_wired() {
+        for layout in LAYOUTS.iter() {
+            for minute in 0..24 * 60 {
+                let time = NaiveTime::from_hms(minute / 60, minute % 60, 0);
+                let face = Face::from_time(time, layout.language, german::Variant::Standard);
+                let wired = layout
+                    .main
+                    .iter()
+                    .filter(|(word, _)| face.main & word != 0)
+                    .fold(0, |wired, (word, _)| wired | word);
+                assert_eq!(wired, face.main, "{:?} {}", layout.language, time);
+                assert_eq!(
+                    layout.lit(&face) >> layout.words.len(),
+                    0,
+                    "{:?} {}",
+                    layout.language,
+                    time
+                );
+            }
+
+            let mut enable: std::vec::Vec<_> =
+                layout.words.iter().map(|word| word.enable).collect();
+            enable.sort_unstable();
+            enable.dedup();
+            assert_eq!(enable.len(), layout.words.len(), "{:?}", layout.language);
+        }
+    }
+
+    #[test]
+    fn full_hours() {
+        let lit = |layout: &Layout, hour| {
+            let time = NaiveTime::from_hms(hour, 0, 0);
+            layout.lit(&Face::from_time(
+                time,
+                layout.language,
+                german::Variant::Standard,
+            ))
+        };
+        // "ES IST ZWEI UHR"
+        assert_eq!(lit(&DEFAULT, 14), 1 << 0 | 1 << 11 | 1 << 1);
+        // "IL EST DEUX HEURES", without a word in the place of "UHR"
+        assert_eq!(lit(&FRENCH, 14), 1 << 0 | 1 << 11 | 1 << 1 | 1 << 23);
+        assert_eq!(lit(&FRENCH, 0), 1 << 0 | 1 << 22);
+        // "SON LAS DOS"
+        assert_eq!(lit(&SPANISH, 14), 1 << 6 | 1 << 11);
+
+        // "ES IST EIN UHR", but "FÜNF NACH EINS"
+        assert_eq!(lit(&GERMAN_EXTENDED, 1), 1 << 0 | 1 << 10 | 1 << 1);
+        assert_eq!(lit(&GERMAN_EXTENDED, 13), 1 << 0 | 1 << 10 | 1 << 1);
+        let five_past = NaiveTime::from_hms(1, 5, 0);
+        let face = Face::from_time(five_past, Language::German, german::Variant::Standard);
+        assert_eq!(
+            GERMAN_EXTENDED.lit(&face),
+            1 << 9 | 1 << 6 | 1 << 10 | 1 << 22
+        );
+        // revision 1 can only show "ES IST EINS UHR"
+        assert_eq!(lit(&DEFAULT, 1), 1 << 0 | 1 << 10 | 1 << 1);
+    }
+
+    #[test]
+    fn midnight() {
+        let lit = |hour, minute| {
+            let time = NaiveTime::from_hms(hour, minute, 0);
+            let face = Face::from_time(time, Language::German, german::Variant::Standard);
+            GERMAN_EXTENDED.lit(&face)
+        };
+        // "ES IST MITTERNACHT"
+        assert_eq!(lit(0, 0), 1 << 0 | 1 << 23);
+        assert_eq!(lit(0, 4), 1 << 0 | 1 << 23);
+        // "ES IST ZWÖLF UHR" at noon
+        assert_eq!(lit(12, 0), 1 << 0 | 1 << 21 | 1 << 1);
+        // "FÜNF NACH ZWÖLF" and "FÜNF VOR ZWÖLF" around midnight
+        assert_eq!(lit(0, 5), 1 << 9 | 1 << 6 | 1 << 21);
+        assert_eq!(lit(23, 55), 1 << 9 | 1 << 3 | 1 << 21);
+        // without the word the face stays at "ZWÖLF UHR"
+        assert_eq!(
+            DEFAULT.lit(&Face::from_time(
+                NaiveTime::from_hms(0, 0, 0),
+                Language::German,
+                german::Variant::Standard
+            )),
+            1 << 0 | 1 << 21 | 1 << 1
+        );
+    }
+}
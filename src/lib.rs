@@ -0,0 +1,32 @@
+//! Hardware independent parts of the word clock
+//!
+//! Decoding of the time signals, time keeping and the phrasing of the clock
+//! face. Everything in here builds on the host as well, where it is tested
+//! with `cargo test-host`.
+#![cfg_attr(not(test), no_std)]
+
+pub mod arbiter;
+pub mod classifier;
+pub mod confidence;
+pub mod dcf77;
+pub mod drift;
+pub mod dutch;
+pub mod english;
+pub mod face;
+pub mod filter;
+pub mod french;
+pub mod generator;
+pub mod german;
+pub mod gps;
+pub mod jjy;
+pub mod layout;
+pub mod msf;
+pub mod nmea;
+pub mod phase;
+pub mod signal;
+pub mod spanish;
+pub mod stats;
+pub mod sync;
+pub mod timezone;
+pub mod trace;
+pub mod wwvb;
@@ -1,23 +1,32 @@
 #![no_std]
 #![no_main]
 mod brightness;
-mod dcf77;
+mod calibration;
+#[cfg(not(feature = "gps"))]
+mod cli;
+mod clock;
 mod display;
+mod receiver;
 
-use chrono::NaiveTime;
+use core::fmt::Write as _;
 use cortex_m;
-use heapless::{consts::*, spsc::Queue};
+use heapless::{
+    consts::*,
+    spsc::{Consumer, Producer, Queue},
+    String, Vec,
+};
 use nb::block;
 use panic_semihosting as _;
 use rtcc::Rtcc;
-use rtic::app;
+use rtic::{app, Mutex};
 use stm32f0xx_hal::{
     adc::Adc,
     counter::CounterTimer,
     delay::Delay,
     gpio::{
         gpiob::{PB6, PB7},
-        Alternate, Output, Pin, PushPull, AF0,
+        gpiof::PF4,
+        Alternate, Floating, Input, Output, Pin, PushPull, AF0,
     },
     pac::{EXTI, TIM1},
     prelude::*,
@@ -28,9 +37,77 @@ use stm32f0xx_hal::{
     stm32::USART1,
     time::U32Ext,
 };
+use word_clock::{arbiter, classifier, dcf77, german, layout, signal::TimeSignal, sync, timezone};
+
+#[cfg(any(
+    all(feature = "jjy", feature = "msf"),
+    all(feature = "jjy", feature = "wwvb"),
+    all(feature = "msf", feature = "wwvb")
+))]
+compile_error!("only one time signal can be selected");
+
+#[cfg(not(any(feature = "jjy", feature = "msf", feature = "wwvb")))]
+type Decoder = dcf77::DCF77;
+#[cfg(feature = "jjy")]
+type Decoder = word_clock::jjy::JJY;
+#[cfg(feature = "msf")]
+type Decoder = word_clock::msf::MSF;
+#[cfg(feature = "wwvb")]
+type Decoder = word_clock::wwvb::WWVB;
+
+/// Local time zone of the display, until another one is set on the console
+///
+/// The RTC runs on UTC.
+const TIMEZONE: &str = "CET-1CEST,M3.5.0,M10.5.0/3";
+
+#[cfg(any(
+    all(feature = "german-extended", feature = "english"),
+    all(feature = "german-extended", feature = "dutch"),
+    all(feature = "german-extended", feature = "french"),
+    all(feature = "german-extended", feature = "spanish"),
+    all(feature = "english", feature = "dutch"),
+    all(feature = "english", feature = "french"),
+    all(feature = "english", feature = "spanish"),
+    all(feature = "dutch", feature = "french"),
+    all(feature = "dutch", feature = "spanish"),
+    all(feature = "french", feature = "spanish")
+))]
+compile_error!("only one language can be selected");
+
+/// Words of the clock face and how they are wired
+#[cfg(not(any(
+    feature = "german-extended",
+    feature = "english",
+    feature = "dutch",
+    feature = "french",
+    feature = "spanish"
+)))]
+const LAYOUT: &layout::Layout = &layout::DEFAULT;
+#[cfg(feature = "german-extended")]
+const LAYOUT: &layout::Layout = &layout::GERMAN_EXTENDED;
+#[cfg(feature = "english")]
+const LAYOUT: &layout::Layout = &layout::ENGLISH;
+#[cfg(feature = "dutch")]
+const LAYOUT: &layout::Layout = &layout::DUTCH;
+#[cfg(feature = "french")]
+const LAYOUT: &layout::Layout = &layout::FRENCH;
+#[cfg(feature = "spanish")]
+const LAYOUT: &layout::Layout = &layout::SPANISH;
+/// German phrasing to use regardless of the Schwaben-Schalter, until another
+/// one is selected on the console
+const VARIANT: Option<german::Variant> = None;
+
+/// Maximum age of a second marker to still synchronise the RTC to it
+const MAX_SYNC_DELAY_MS: u32 = 500;
+
+/// The GPS is connected to the serial port instead of the console
+#[cfg(feature = "gps")]
+const SERIAL_BAUD: u32 = 9_600;
+#[cfg(not(feature = "gps"))]
+const SERIAL_BAUD: u32 = 115_200;
 
 pub struct SerialBuffer {
-    queue: Queue<u8, U32>,
+    queue: Queue<u8, U128>,
 }
 
 impl SerialBuffer {
@@ -56,21 +133,73 @@ impl core::fmt::Write for SerialBuffer {
     }
 }
 
+/// Phrasing `selected` or set by the Schwaben-Schalter
+fn variant(
+    selected: Option<german::Variant>,
+    schwaben_schalter: &PF4<Input<Floating>>,
+) -> german::Variant {
+    selected.unwrap_or_else(|| german::Variant::from_switch(schwaben_schalter.is_high().unwrap()))
+}
+
+/// Time zone given by the POSIX TZ `rules`, along with the rules to show
+///
+/// Invalid rules fall back to UTC, a clock showing the wrong time zone can
+/// still be corrected on the console.
+fn local_timezone(rules: &str) -> (timezone::TimeZone, String<U48>) {
+    let mut name = String::new();
+    match (timezone::TimeZone::parse(rules), name.push_str(rules)) {
+        (Ok(timezone), Ok(())) => (timezone, name),
+        _ => (timezone::TimeZone::UTC, String::from("UTC0")),
+    }
+}
+
+/// Whether an interrupt of EXTI `line` is pending, clearing it
+fn exti_pending(line: u8) -> bool {
+    let exti = unsafe { &*EXTI::ptr() };
+    let pending = exti.pr.read().bits() & (1 << line) != 0;
+    if pending {
+        unsafe { exti.pr.write(|w| w.bits(1 << line)) }
+    }
+    pending
+}
+
 #[app(device=stm32f0xx_hal::pac, peripherals = true)]
 const APP: () = {
     struct Resources {
         words: display::WordDisplay<Pin<Output<PushPull>>>,
         minutes: display::MinuteDisplay<Pin<Output<PushPull>>>,
+        schwaben_schalter: PF4<Input<Floating>>,
+        /// phrasing selected on the console, otherwise by the switch
+        variant: Option<german::Variant>,
         brightness: brightness::BrightnessControl,
-        dcf77: dcf77::DCF77<CounterTimer<TIM1>>,
+        receiver: receiver::Receiver<CounterTimer<TIM1>>,
+        edge_producer: Producer<'static, receiver::Timestamp, U32>,
+        edge_consumer: Consumer<'static, receiver::Timestamp, U32>,
+        decoder: Decoder,
         rtc: Rtc,
+        timezone: timezone::TimeZone,
+        /// rules `timezone` has been given by
+        #[cfg(not(feature = "gps"))]
+        timezone_rules: String<U48>,
+        sync: sync::RtcSync,
+        arbiter: arbiter::Arbiter,
+        calibration: calibration::Calibration,
         delay: Delay,
         serial: Serial<USART1, PB6<Alternate<AF0>>, PB7<Alternate<AF0>>>,
         serial_queue: SerialBuffer,
+        #[cfg(not(feature = "gps"))]
+        console: menu::Runner<'static, cli::Output>,
+        #[cfg(feature = "gps")]
+        gps: word_clock::gps::GpsTime,
     }
 
     #[init()]
     fn init(cx: init::Context) -> init::LateResources {
+        // edges captured in the interrupt, waiting to be decoded
+        static mut EDGES: Queue<receiver::Timestamp, U32> = Queue(heapless::i::Queue::new());
+        // line typed on the console, long enough for the time zone rules
+        static mut COMMAND: [u8; 64] = [0; 64];
+
         cortex_m::interrupt::free(move |cs| {
             let cp: cortex_m::Peripherals = cx.core;
             let dp: stm32f0xx_hal::pac::Peripherals = cx.device;
@@ -85,15 +214,17 @@ const APP: () = {
             let gpioa = dp.GPIOA.split(&mut rcc);
             let gpiob = dp.GPIOB.split(&mut rcc);
             let gpioc = dp.GPIOC.split(&mut rcc);
+            let gpiod = dp.GPIOD.split(&mut rcc);
             let gpiof = dp.GPIOF.split(&mut rcc);
 
-            // TODO setup synchronisation to DCF77 pulses
             let mut exti = dp.EXTI;
             let mut pwr = dp.PWR;
             let mut rtc = Rtc::open_or_init(&mut rcc, &mut pwr, dp.RTC, 255, 127, false);
             // setup alarm to trigger interrupt on every full minute
             rtc.listen(&mut exti, Event::AlarmA);
             rtc.set_alarm(Alarm::alarm().subseconds(8, 0)).unwrap();
+            // restore the drift compensation from the backup registers
+            let calibration = calibration::Calibration::init();
 
             //let time = rtc.get_time().unwrap();
             //hprintln!("{}:{}:{}", time.hour(), time.minute(), time.second()).unwrap_or(());
@@ -108,11 +239,30 @@ const APP: () = {
             exti.rtsr.modify(|_, w| w.tr3().set_bit());
             exti.ftsr.modify(|_, w| w.tr3().set_bit());
 
-            let dcf77 = dcf77::DCF77::init(
+            #[cfg(feature = "gps")]
+            {
+                let _pps_pin = gpioa.pa8.into_pull_down_input(cs);
+                // enable exti for PA8
+                syscfg.exticr3.modify(|_, w| unsafe { w.exti8().bits(0) });
+                exti.imr.modify(|_, w| w.mr8().set_bit());
+                // the pulse starts the second with its rising edge
+                exti.rtsr.modify(|_, w| w.tr8().set_bit());
+            }
+
+            let receiver = receiver::Receiver::init(
                 CounterTimer::tim1(dp.TIM1, 1.khz(), &mut rcc),
                 dcf77_pin.downgrade(),
                 true,
             );
+            let (edge_producer, edge_consumer) = EDGES.split();
+            #[cfg(not(any(feature = "jjy", feature = "msf", feature = "wwvb")))]
+            let decoder = {
+                let mut dcf77 = dcf77::DCF77::init(classifier::PulseClassifier::default(), 2);
+                dcf77.set_correction(Some(2));
+                dcf77
+            };
+            #[cfg(any(feature = "jjy", feature = "msf", feature = "wwvb"))]
+            let decoder = Decoder::init(2);
 
             let words_pwm = pwm::tim2(
                 dp.TIM2,
@@ -138,8 +288,8 @@ const APP: () = {
             let mut bright_ctl =
                 brightness::BrightnessControl::init(words_pwm, minutes_pwm, adc, pd1_in);
 
-            let mut word_display = display::WordDisplay::init(
-                gpioa.pa6.into_push_pull_output(cs).downgrade(),
+            // enable pins in the order of the words of the layout
+            let mut word_pins: Vec<_, U32> = IntoIterator::into_iter([
                 gpiob.pb8.into_push_pull_output(cs).downgrade(),
                 gpioc.pc1.into_push_pull_output(cs).downgrade(),
                 gpioc.pc7.into_push_pull_output(cs).downgrade(),
@@ -162,14 +312,38 @@ const APP: () = {
                 gpioc.pc2.into_push_pull_output(cs).downgrade(),
                 gpioc.pc6.into_push_pull_output(cs).downgrade(),
                 gpioa.pa1.into_push_pull_output(cs).downgrade(),
-                gpioa.pa3.into_push_pull_output(cs).downgrade(),
-                gpiob.pb1.into_push_pull_output(cs).downgrade(),
-                gpiob.pb0.into_push_pull_output(cs).downgrade(),
-                gpiof.pf5.into_push_pull_output(cs).downgrade(),
-                gpioa.pa7.into_push_pull_output(cs).downgrade(),
-                gpiob.pb2.into_push_pull_output(cs).downgrade(),
-                gpioc.pc5.into_push_pull_output(cs).downgrade(),
-                gpioa.pa4.into_push_pull_output(cs).downgrade(),
+            ])
+            .collect();
+            // spare pins, for faces with more words than board revision 1,
+            // left analog unless the layout wires them
+            macro_rules! spare_pin {
+                ($pin:expr) => {
+                    if word_pins.len() < LAYOUT.words.len() {
+                        word_pins
+                            .push($pin.into_push_pull_output(cs).downgrade())
+                            .ok();
+                    } else {
+                        $pin.into_analog(cs);
+                    }
+                };
+            }
+            spare_pin!(gpioc.pc4);
+            spare_pin!(gpioc.pc13);
+            spare_pin!(gpiod.pd2);
+            let mut word_display = display::WordDisplay::init(
+                LAYOUT,
+                gpioa.pa6.into_push_pull_output(cs).downgrade(),
+                word_pins,
+                [
+                    gpioa.pa3.into_push_pull_output(cs).downgrade(),
+                    gpiob.pb1.into_push_pull_output(cs).downgrade(),
+                    gpiob.pb0.into_push_pull_output(cs).downgrade(),
+                    gpiof.pf5.into_push_pull_output(cs).downgrade(),
+                    gpioa.pa7.into_push_pull_output(cs).downgrade(),
+                    gpiob.pb2.into_push_pull_output(cs).downgrade(),
+                    gpioc.pc5.into_push_pull_output(cs).downgrade(),
+                    gpioa.pa4.into_push_pull_output(cs).downgrade(),
+                ],
             )
             .unwrap();
 
@@ -190,31 +364,55 @@ const APP: () = {
                     gpiob.pb6.into_alternate_af0(cs),
                     gpiob.pb7.into_alternate_af0(cs),
                 ),
-                115_200.bps(),
+                SERIAL_BAUD.bps(),
                 &mut rcc,
             );
             serial.listen(Rxne);
-            // TODO implement schwaben schalter
-            let _schwaben_schalter = gpiof.pf4.into_floating_input(cs);
-
-            let _time = NaiveTime::from_hms(11, 19, 42);
-            let time = rtc.get_time().unwrap();
-
-            word_display.test().unwrap();
-            word_display.set_time(time).unwrap();
-            // minute_display.set_time(time).unwrap();
+            #[cfg(not(feature = "gps"))]
+            let console = menu::Runner::new(&cli::MENU, COMMAND, cli::Output::new());
+            let schwaben_schalter = gpiof.pf4.into_floating_input(cs);
+            // enable exti for PF4, the face changes as soon as it is switched
+            syscfg.exticr2.modify(|_, w| unsafe { w.exti4().bits(5) });
+            exti.imr.modify(|_, w| w.mr4().set_bit());
+            exti.rtsr.modify(|_, w| w.tr4().set_bit());
+            exti.ftsr.modify(|_, w| w.tr4().set_bit());
+
+            #[cfg_attr(feature = "gps", allow(unused_variables))]
+            let (timezone, timezone_rules) = local_timezone(TIMEZONE);
+
+            // a face not shown yet is updated with the next minute
+            word_display.set_variant(variant(VARIANT, &schwaben_schalter));
+            word_display.test().ok();
+            if let Ok(utc) = rtc.get_datetime() {
+                word_display.set_time(timezone.to_local(utc).time()).ok();
+            }
 
             bright_ctl.update();
 
             init::LateResources {
                 words: word_display,
                 minutes: minute_display,
+                schwaben_schalter,
+                variant: VARIANT,
                 brightness: bright_ctl,
-                dcf77,
+                receiver,
+                edge_producer,
+                edge_consumer,
+                decoder,
                 rtc,
+                timezone,
+                #[cfg(not(feature = "gps"))]
+                timezone_rules,
+                sync: sync::RtcSync::new(),
+                arbiter: arbiter::Arbiter::new(),
+                calibration,
                 delay,
                 serial,
                 serial_queue,
+                #[cfg(not(feature = "gps"))]
+                console,
+                #[cfg(feature = "gps")]
+                gps: word_clock::gps::GpsTime::new(),
             }
         })
     }
@@ -228,15 +426,43 @@ const APP: () = {
 
     #[task(resources = [serial, serial_queue])]
     fn process_serial(cx: process_serial::Context) {
-        while let Some(b) = cx.resources.serial_queue.queue.dequeue() {
-            block!(cx.resources.serial.write(b)).unwrap();
+        let process_serial::Resources {
+            serial,
+            serial_queue,
+        } = cx.resources;
+        // only shared with the GPS
+        #[cfg(feature = "gps")]
+        let mut serial = serial;
+        #[cfg(not(feature = "gps"))]
+        let mut serial = rtic::Exclusive(serial);
+
+        while let Some(b) = serial_queue.queue.dequeue() {
+            serial.lock(|serial| block!(serial.write(b))).unwrap();
         }
     }
 
-    #[task(binds=RTC, resources = [brightness, rtc, words, minutes, serial, delay])]
-    fn rtc(cx: rtc::Context) {
+    #[task(binds=RTC, resources=[rtc], spawn=[show_time])]
+    fn rtc(mut cx: rtc::Context) {
         // RTC interrupt triggered on the start of every minute
-        let time = cx.resources.rtc.get_time().unwrap();
+        cx.spawn.show_time().ok();
+
+        cx.resources
+            .rtc
+            .lock(|rtc| rtc.clear_interrupt(Event::AlarmA))
+    }
+
+    #[task(
+        resources = [brightness, rtc, timezone, words, minutes, schwaben_schalter, variant, delay],
+    )]
+    fn show_time(mut cx: show_time::Context) {
+        let utc = match cx.resources.rtc.lock(|rtc| rtc.get_datetime()) {
+            Ok(utc) => utc,
+            // keep the face, the next minute tries again
+            Err(_) => return,
+        };
+        let time = cx.resources.timezone.to_local(utc).time();
+        let variant = variant(*cx.resources.variant, cx.resources.schwaben_schalter);
+        cx.resources.words.set_variant(variant);
 
         //cx.resources.serial.lock(|&mut s| {
         //    write!(s, "{}:{}:{}\n", time.hour(), time.minute(), time.second()).unwrap();
@@ -244,31 +470,266 @@ const APP: () = {
 
         //hprintln!("{}:{}:{}", time.hour(), time.minute(), time.second()).unwrap_or(());
 
+        // a face which could not be set still needs an update, and is retried
+        // with the next minute
         if cx.resources.words.needs_update(time) {
             cx.resources.brightness.dim_down(cx.resources.delay);
-            cx.resources.words.set_time(time).unwrap();
-            cx.resources.minutes.set_time(time).unwrap();
+            cx.resources.words.set_time(time).ok();
+            cx.resources.minutes.set_time(time).ok();
             cx.resources.delay.delay_ms(250u16);
             cx.resources.brightness.dim_up(cx.resources.delay);
         } else {
-            cx.resources.minutes.set_time(time).unwrap();
+            cx.resources.minutes.set_time(time).ok();
         }
 
         // update brightness based on PD light level
         cx.resources.brightness.update();
-
-        cx.resources.rtc.clear_interrupt(Event::AlarmA)
     }
 
-    #[task(binds=EXTI2_3, resources=[dcf77], priority=2)]
+    #[task(binds=EXTI2_3, resources=[receiver, edge_producer], spawn=[decode], priority=3)]
     fn dcf77_pin(cx: dcf77_pin::Context) {
-        cx.resources.dcf77.update_state().unwrap();
+        // a lost edge only costs the frame currently received
+        let edge = cx.resources.receiver.edge();
+        cx.resources.edge_producer.enqueue(edge).ok();
+        // already pending if the queue is not drained yet
+        cx.spawn.decode().ok();
 
         // clear exti pending bit
         unsafe { (*EXTI::ptr()).pr.write(|w| w.pr3().set_bit()) }
     }
 
+    #[task(
+        resources=[receiver, edge_consumer, decoder, rtc, sync, arbiter, calibration],
+        priority=2
+    )]
+    fn decode(cx: decode::Context) {
+        // preempts the display update, which waits for the dimming
+        static mut LAST_EDGE_MS: u32 = 0;
+
+        let decode::Resources {
+            mut receiver,
+            edge_consumer,
+            decoder,
+            rtc,
+            sync,
+            arbiter,
+            calibration,
+        } = cx.resources;
+        // only locked against the GPS tasks
+        #[cfg(feature = "gps")]
+        let (mut rtc, mut sync, mut arbiter, mut calibration) = (rtc, sync, arbiter, calibration);
+        #[cfg(not(feature = "gps"))]
+        let (mut rtc, mut sync, mut arbiter, mut calibration) = (
+            rtic::Exclusive(rtc),
+            rtic::Exclusive(sync),
+            rtic::Exclusive(arbiter),
+            rtic::Exclusive(calibration),
+        );
+
+        while let Some(edge) = edge_consumer.dequeue() {
+            decoder.update(edge.rising, edge.time_ms.wrapping_sub(*LAST_EDGE_MS));
+            *LAST_EDGE_MS = edge.time_ms;
+            if !decoder.second_started() {
+                continue;
+            }
+
+            // the RTC is set to the second marker after the fact
+            let delay_ms = receiver
+                .lock(|receiver| receiver.now_ms())
+                .wrapping_sub(edge.time_ms);
+            if delay_ms > MAX_SYNC_DELAY_MS {
+                continue;
+            }
+
+            let time = decoder.now();
+            let (leap_second, confidence) = (decoder.leap_second(), decoder.confidence());
+            let result = rtc.lock(|rtc| {
+                sync.lock(|sync| {
+                    if let (true, Ok(time)) = (leap_second, time) {
+                        sync.announce_leap_second(time);
+                    }
+                    arbiter.lock(|arbiter| {
+                        arbiter.second_marker(
+                            rtc,
+                            sync,
+                            arbiter::Source::Radio,
+                            confidence,
+                            time,
+                            delay_ms.into(),
+                        )
+                    })
+                })
+            });
+            if let (Ok(time), Ok(Some(result))) = (time, result) {
+                calibration.lock(|calibration| calibration.update(time, result));
+            }
+        }
+    }
+
+    #[cfg(not(feature = "gps"))]
+    #[task(
+        binds=USART1,
+        resources=[serial, console, rtc, arbiter, calibration, variant, timezone, timezone_rules],
+        spawn=[show_time]
+    )]
+    fn console_serial(cx: console_serial::Context) {
+        let console_serial::Resources {
+            serial,
+            console,
+            mut rtc,
+            mut arbiter,
+            mut calibration,
+            variant,
+            timezone,
+            timezone_rules,
+        } = cx.resources;
+
+        loop {
+            match serial.read() {
+                Ok(byte) => console.input_byte(byte),
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(_)) => (),
+            }
+
+            let output = &mut console.context;
+            match output.request.take() {
+                Some(cli::Request::ReadTime) => {
+                    match rtc.lock(|rtc| rtc.get_datetime()) {
+                        Ok(time) => {
+                            let source = arbiter.lock(|arbiter| arbiter.active());
+                            writeln!(output, "{} UTC, {:?}", time, source)
+                        }
+                        Err(_) => writeln!(output, "RTC not readable"),
+                    }
+                    .ok();
+                }
+                Some(cli::Request::SetTime(time)) => {
+                    match rtc.lock(|rtc| arbiter.lock(|arbiter| arbiter.set_manual(rtc, time))) {
+                        Ok(()) => {
+                            // the drift since the last sync is unknown now
+                            calibration.lock(|calibration| calibration.reset());
+                            writeln!(output, "time set to {} UTC", time)
+                        }
+                        Err(_) => writeln!(output, "RTC not writable"),
+                    }
+                    .ok();
+                }
+                Some(cli::Request::ReadVariant) => {
+                    match variant {
+                        Some(variant) => writeln!(output, "{}", variant.name()),
+                        None => writeln!(output, "switch"),
+                    }
+                    .ok();
+                }
+                Some(cli::Request::SetVariant(selected)) => {
+                    *variant = selected;
+                    cx.spawn.show_time().ok();
+                }
+                Some(cli::Request::ReadTimeZone) => {
+                    writeln!(output, "{}", timezone_rules).ok();
+                }
+                Some(cli::Request::SetTimeZone(selected, rules)) => {
+                    *timezone = selected;
+                    *timezone_rules = rules;
+                    cx.spawn.show_time().ok();
+                }
+                None => (),
+            }
+
+            while let Some(byte) = output.buffer.queue.dequeue() {
+                block!(serial.write(byte)).ok();
+            }
+        }
+    }
+
+    #[cfg(feature = "gps")]
+    #[task(binds=USART1, resources=[serial, gps, rtc, sync, arbiter], priority=3)]
+    fn gps_serial(cx: gps_serial::Context) {
+        loop {
+            match cx.resources.serial.read() {
+                Ok(byte) => {
+                    // without PPS the sentences set the time, but not its phase
+                    if let Ok(time) = cx.resources.gps.byte(byte) {
+                        cx.resources
+                            .arbiter
+                            .coarse_time(
+                                cx.resources.rtc,
+                                cx.resources.sync,
+                                arbiter::Source::Gps,
+                                word_clock::confidence::Confidence::Tentative(1),
+                                time,
+                                word_clock::gps::SENTENCE_DELAY_MS,
+                            )
+                            .ok();
+                    }
+                }
+                Err(nb::Error::WouldBlock) => break,
+                // the error flag is cleared, the sentence fails its checksum
+                Err(nb::Error::Other(_)) => (),
+            }
+        }
+    }
+
+    #[cfg(feature = "gps")]
+    #[task(
+        binds=EXTI4_15,
+        resources=[gps, rtc, sync, arbiter, calibration],
+        spawn=[show_time],
+        priority=3
+    )]
+    fn gps_pps(cx: gps_pps::Context) {
+        // the Schwaben-Schalter shares the interrupt
+        if exti_pending(4) {
+            cx.spawn.show_time().ok();
+        }
+        if !exti_pending(8) {
+            return;
+        }
+
+        match cx.resources.gps.pulse() {
+            Some((time, word_clock::confidence::Confidence::Confirmed)) => {
+                let result = cx.resources.arbiter.second_marker(
+                    cx.resources.rtc,
+                    cx.resources.sync,
+                    arbiter::Source::Gps,
+                    word_clock::confidence::Confidence::Confirmed,
+                    Ok(time),
+                    0,
+                );
+                if let Ok(Some(result)) = result {
+                    cx.resources.calibration.update(time, result);
+                }
+            }
+            // without a recent valid fix the pulse may come from the free
+            // running clock of the receiver
+            Some((time, confidence)) => {
+                cx.resources
+                    .arbiter
+                    .coarse_time(
+                        cx.resources.rtc,
+                        cx.resources.sync,
+                        arbiter::Source::Gps,
+                        confidence,
+                        time,
+                        0,
+                    )
+                    .ok();
+            }
+            None => (),
+        }
+    }
+
+    #[cfg(not(feature = "gps"))]
+    #[task(binds=EXTI4_15, spawn=[show_time])]
+    fn switch_changed(cx: switch_changed::Context) {
+        // bouncing runs the update at most once more
+        if exti_pending(4) {
+            cx.spawn.show_time().ok();
+        }
+    }
+
     extern "C" {
         fn I2C1();
+        fn SPI1();
     }
 };
@@ -0,0 +1,312 @@
+//! MSF, transmitted from Anthorn in the UK on 60kHz
+//!
+//! Every second carries two bits A and B, the minute marker is a 500ms pulse
+//! at its start. The time refers to the minute starting with the following
+//! minute marker and is UK civil time, `cest` of the frame signals British
+//! summer time.
+
+use crate::dcf77::{infer_century, DCF77Frame, Error};
+use crate::generator::{Encoder, Flags};
+use crate::signal::{insert_weighted, matches, weighted, Decoder, Field, Pulses, Station};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Weekday};
+
+const TOLERANCE_MS: u32 = 40;
+/// Fixed pattern of the A bits 52 to 59
+const MARKER_BITS: u64 = 0b0111_1110 << 52;
+
+const YEAR: Field = (17, &[80, 40, 20, 10, 8, 4, 2, 1]);
+const MONTH: Field = (25, &[10, 8, 4, 2, 1]);
+const DAY: Field = (30, &[20, 10, 8, 4, 2, 1]);
+const WEEKDAY: Field = (36, &[4, 2, 1]);
+const HOUR: Field = (39, &[20, 10, 8, 4, 2, 1]);
+const MINUTE: Field = (45, &[40, 20, 10, 8, 4, 2, 1]);
+/// A bits covered by the parity in the B bit, with the error reported
+const PARITIES: [(usize, usize, usize, Error); 4] = [
+    (17, 24, 54, Error::ParityErrorDate),
+    (25, 35, 55, Error::ParityErrorDate),
+    (36, 38, 56, Error::ParityErrorDate),
+    (39, 51, 57, Error::ParityErrorMinute),
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    Bits { a: bool, b: bool },
+    MinuteMarker,
+}
+
+pub struct Msf;
+
+pub type MSF = Decoder<Msf>;
+
+/// Odd parity over the A bits in `range` and the B bit `parity`
+fn check_parity(a: u64, b: u64, first: usize, last: usize, parity: usize) -> bool {
+    let ones = (first..=last).filter(|bit| (a >> bit) & 1 == 1).count();
+    (ones + ((b >> parity) & 1) as usize) % 2 == 1
+}
+
+impl Station for Msf {
+    type Symbol = Symbol;
+
+    fn symbol(pulses: &[(u32, u32)]) -> Option<Symbol> {
+        let bits = |a, b| Some(Symbol::Bits { a, b });
+        match *pulses {
+            [(0, width)] if matches(width, 100, TOLERANCE_MS) => bits(false, false),
+            [(0, width)] if matches(width, 200, TOLERANCE_MS) => bits(true, false),
+            [(0, width)] if matches(width, 300, TOLERANCE_MS) => bits(true, true),
+            [(0, width)] if matches(width, 500, TOLERANCE_MS) => Some(Symbol::MinuteMarker),
+            [(0, first), (start, second)]
+                if matches(first, 100, TOLERANCE_MS)
+                    && matches(start, 200, TOLERANCE_MS)
+                    && matches(second, 100, TOLERANCE_MS) =>
+            {
+                bits(false, true)
+            }
+            _ => None,
+        }
+    }
+
+    fn minute_start(_previous: Option<Symbol>, symbol: Option<Symbol>) -> bool {
+        symbol == Some(Symbol::MinuteMarker)
+    }
+
+    fn decode(
+        symbols: &[Option<Symbol>],
+        _previous: Option<&DCF77Frame>,
+    ) -> Result<DCF77Frame, Error> {
+        // a leap second is inserted before second 17
+        let len = symbols.len();
+        if !(59..=61).contains(&len) {
+            return Err(Error::ProtocolError);
+        }
+
+        let (mut a, mut b) = (0u64, 0u64);
+        for bit in 17..60 {
+            match symbols[bit + len - 60] {
+                Some(Symbol::Bits { a: bit_a, b: bit_b }) => {
+                    a |= (bit_a as u64) << bit;
+                    b |= (bit_b as u64) << bit;
+                }
+                Some(Symbol::MinuteMarker) => return Err(Error::StartOfMinuteError),
+                None => return Err(Error::UnreadableBit),
+            }
+        }
+
+        if a & (0xFF << 52) != MARKER_BITS {
+            return Err(Error::StartOfMinuteError);
+        }
+        for &(first, last, parity, error) in PARITIES.iter() {
+            if !check_parity(a, b, first, last, parity) {
+                return Err(error);
+            }
+        }
+
+        let weekday = match weighted(a, WEEKDAY)? {
+            0 => Weekday::Sun,
+            1 => Weekday::Mon,
+            2 => Weekday::Tue,
+            3 => Weekday::Wed,
+            4 => Weekday::Thu,
+            5 => Weekday::Fri,
+            6 => Weekday::Sat,
+            _ => return Err(Error::InvalidDate),
+        };
+        let (day, month) = (weighted(a, DAY)?, weighted(a, MONTH)?);
+        let date = infer_century(weighted(a, YEAR)?, weekday, |year| {
+            NaiveDate::from_ymd_opt(year, month, day)
+        })?;
+        let summer_time = (b >> 58) & 1 == 1;
+
+        let frame = DCF77Frame {
+            call_bit: false,
+            dst_announcement: (b >> 53) & 1 == 1,
+            cest: summer_time,
+            cet: !summer_time,
+            leap_second_announcement: false,
+            minute: weighted(a, MINUTE)?,
+            hour: weighted(a, HOUR)?,
+            day,
+            weekday,
+            month,
+            year: date.year(),
+        };
+        frame.datetime(0)?;
+        Ok(frame)
+    }
+
+    fn utc_offset(frame: &DCF77Frame) -> Duration {
+        Duration::hours(frame.cest.into())
+    }
+}
+
+/// A and B bits of the minute carrying `time`
+pub fn encode(time: NaiveDateTime, flags: &Flags) -> (u64, u64) {
+    let (mut a, mut b) = (MARKER_BITS, 0u64);
+    insert_weighted(&mut a, YEAR, (time.year() % 100) as u32);
+    insert_weighted(&mut a, MONTH, time.month());
+    insert_weighted(&mut a, DAY, time.day());
+    insert_weighted(&mut a, WEEKDAY, time.weekday().num_days_from_sunday());
+    insert_weighted(&mut a, HOUR, time.hour());
+    insert_weighted(&mut a, MINUTE, time.minute());
+
+    b |= (flags.dst_announcement as u64) << 53;
+    b |= (flags.summer_time as u64) << 58;
+    for &(first, last, parity, _) in PARITIES.iter() {
+        if !check_parity(a, b, first, last, parity) {
+            b |= 1 << parity;
+        }
+    }
+
+    (a, b)
+}
+
+impl Encoder for Msf {
+    fn encode_second(minute: NaiveDateTime, flags: &Flags, second: u32) -> Option<Pulses> {
+        let (a, b) = encode(minute + Duration::minutes(1), flags);
+        let mut pulses = Pulses::new();
+        let pulse = |bits: u64| (bits >> second) & 1 == 1;
+        match (second, pulse(a), pulse(b)) {
+            (0, _, _) => pulses.push((0, 500)).ok(),
+            (60..=u32::MAX, _, _) => return None,
+            (_, false, false) => pulses.push((0, 100)).ok(),
+            (_, true, false) => pulses.push((0, 200)).ok(),
+            (_, true, true) => pulses.push((0, 300)).ok(),
+            (_, false, true) => pulses
+                .push((0, 100))
+                .and_then(|_| pulses.push((200, 100)))
+                .ok(),
+        };
+        Some(pulses)
+    }
+
+    fn flip(pulses: &mut Pulses) {
+        // toggle the A bit
+        let flipped: &[(u32, u32)] = match **pulses {
+            [(0, 100)] => &[(0, 200)],
+            [(0, 200)] => &[(0, 100)],
+            [(0, 300)] => &[(0, 100), (200, 100)],
+            [(0, 100), (200, 100)] => &[(0, 300)],
+            _ => return,
+        };
+        *pulses = Pulses::from_slice(flipped).unwrap_or_default();
+    }
+
+    fn utc_offset(flags: &Flags) -> Duration {
+        Duration::hours(flags.summer_time.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::{check, run, MILD};
+    use crate::generator::{Generator, Impairments};
+    use crate::trace::Replay;
+
+    fn time(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2024, 2, 28).and_hms(hour, minute, 0)
+    }
+
+    /// Symbols of a minute with the bits `a` and `b`
+    fn symbols((a, b): (u64, u64)) -> std::vec::Vec<Option<Symbol>> {
+        core::iter::once(Some(Symbol::MinuteMarker))
+            .chain((1..60).map(|bit| {
+                Some(Symbol::Bits {
+                    a: (a >> bit) & 1 == 1,
+                    b: (b >> bit) & 1 == 1,
+                })
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let flags = Flags {
+            summer_time: true,
+            dst_announcement: true,
+            ..Flags::default()
+        };
+        let generator = Generator::<Msf>::new(time(23, 55), flags, Impairments::default(), 1);
+        // every second from the end of the third minute marker on
+        check(run(generator, MSF::init(2), 10), 7 * 60 - 1);
+
+        let generator = Generator::<Msf>::new(time(23, 55), flags, Impairments::default(), 1);
+        // the third minute marker is decoded once the following second starts
+        let mut replay = Replay::new(MSF::init(2));
+        for edge in generator.take_while(|edge| edge.time_ms <= 3 * 60_000 + 1000) {
+            replay.edge(&edge).unwrap();
+        }
+        let frame = replay.decoder().frame().unwrap();
+        assert_eq!(frame.datetime(0), Ok(time(23, 58)));
+        assert!(frame.cest && !frame.cet && frame.dst_announcement);
+    }
+
+    #[test]
+    fn noisy() {
+        for seed in 1..5 {
+            let generator = Generator::<Msf>::new(time(23, 55), Flags::default(), MILD, seed);
+            check(run(generator, MSF::init(2), 20), 14 * 60);
+        }
+    }
+
+    #[test]
+    fn parity_bits() {
+        let (a, b) = encode(time(13, 37), &Flags::default());
+        for &(first, last, parity, _) in PARITIES.iter() {
+            assert!(check_parity(a, b, first, last, parity));
+        }
+        assert!(Msf::decode(&symbols((a, b)), None).is_ok());
+
+        for &(first, last, parity, error) in PARITIES.iter() {
+            // a wrong parity bit and a wrong bit covered by it
+            let frame = Msf::decode(&symbols((a, b ^ (1 << parity))), None);
+            assert_eq!(frame.unwrap_err(), error);
+            for bit in first..=last {
+                let frame = Msf::decode(&symbols((a ^ (1 << bit), b)), None);
+                assert_eq!(frame.unwrap_err(), error, "bit {}", bit);
+            }
+        }
+    }
+
+    #[test]
+    fn frame() {
+        let flags = Flags {
+            summer_time: true,
+            ..Flags::default()
+        };
+        let frame = Msf::decode(&symbols(encode(time(13, 37), &flags)), None).unwrap();
+        assert_eq!(frame.datetime(0), Ok(time(13, 37)));
+        assert_eq!(frame.weekday, Weekday::Wed);
+        assert!(frame.cest && !frame.dst_announcement);
+        assert_eq!(<Msf as Station>::utc_offset(&frame), Duration::hours(1));
+
+        // a leap second is inserted before second 17
+        let mut leap = symbols(encode(time(13, 37), &flags));
+        leap.insert(16, Some(Symbol::Bits { a: false, b: false }));
+        assert_eq!(Msf::decode(&leap, None), Ok(frame));
+
+        let mut marker_bits = symbols(encode(time(13, 37), &flags));
+        marker_bits[52] = Some(Symbol::Bits { a: true, b: false });
+        assert_eq!(
+            Msf::decode(&marker_bits, None).unwrap_err(),
+            Error::StartOfMinuteError
+        );
+        assert_eq!(Msf::decode(&leap[..58], None), Err(Error::ProtocolError));
+
+        // and second 16 is left out for a negative one
+        let mut negative = symbols(encode(time(13, 37), &flags));
+        negative.remove(16);
+        assert_eq!(Msf::decode(&negative, None), Ok(frame));
+    }
+
+    #[test]
+    fn symbols_from_pulses() {
+        let bits = |a, b| Some(Symbol::Bits { a, b });
+        assert_eq!(Msf::symbol(&[(0, 110)]), bits(false, false));
+        assert_eq!(Msf::symbol(&[(0, 190)]), bits(true, false));
+        assert_eq!(Msf::symbol(&[(0, 320)]), bits(true, true));
+        assert_eq!(Msf::symbol(&[(0, 100), (210, 90)]), bits(false, true));
+        assert_eq!(Msf::symbol(&[(0, 480)]), Some(Symbol::MinuteMarker));
+        assert_eq!(Msf::symbol(&[(0, 400)]), None);
+        assert_eq!(Msf::symbol(&[]), None);
+    }
+}
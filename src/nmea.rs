@@ -0,0 +1,288 @@
+//! Parser for the time carrying NMEA 0183 sentences of GPS receivers
+//!
+//! Understood are RMC and ZDA sentences of any talker, e.g. `$GPRMC`,
+//! `$GNRMC` and `$GPZDA`. All other sentences are skipped. The times are UTC.
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use heapless::{consts::*, Vec};
+use nb::Error::{Other, WouldBlock};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// sentence longer than the 82 characters allowed
+    Overflow,
+    /// missing or wrong checksum
+    Checksum,
+    /// malformed field
+    Syntax,
+    /// the receiver has no valid time yet
+    NoFix,
+    /// date or time out of range
+    InvalidTime,
+}
+
+/// Kind of a sentence carrying the time
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sentence {
+    /// RMC, only accepted with a valid fix
+    Rmc,
+    /// ZDA, which receivers also send from their own clock without a fix
+    Zda,
+}
+
+/// Parser of the sentences received byte by byte
+pub struct NmeaParser {
+    /// sentence without the leading `$`
+    buffer: Vec<u8, U82>,
+    /// a sentence has been started
+    in_sentence: bool,
+}
+
+impl Default for NmeaParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NmeaParser {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            in_sentence: false,
+        }
+    }
+
+    /// Add the next received byte
+    ///
+    /// Returns the time once a sentence carrying it is complete. A `$` always
+    /// starts a new sentence, so a partial one after lost bytes is dropped.
+    pub fn push(&mut self, byte: u8) -> nb::Result<(Sentence, NaiveDateTime), Error> {
+        match byte {
+            b'$' => {
+                self.buffer = Vec::new();
+                self.in_sentence = true;
+                Err(WouldBlock)
+            }
+            b'\r' | b'\n' if self.in_sentence => {
+                self.in_sentence = false;
+                match parse(&self.buffer) {
+                    Ok(Some(time)) => Ok(time),
+                    Ok(None) => Err(WouldBlock),
+                    Err(error) => Err(Other(error)),
+                }
+            }
+            _ if self.in_sentence => {
+                if self.buffer.push(byte).is_err() {
+                    self.in_sentence = false;
+                    return Err(Other(Error::Overflow));
+                }
+                Err(WouldBlock)
+            }
+            _ => Err(WouldBlock),
+        }
+    }
+}
+
+/// Parse a sentence without the leading `$` and the line ending
+///
+/// Returns `None` for sentences not carrying the time.
+pub fn parse(sentence: &[u8]) -> Result<Option<(Sentence, NaiveDateTime)>, Error> {
+    if !sentence.is_ascii() {
+        return Err(Error::Syntax);
+    }
+    let sentence = core::str::from_utf8(sentence).map_err(|_| Error::Syntax)?;
+    let star = sentence.rfind('*').ok_or(Error::Checksum)?;
+    let (data, checksum) = (&sentence[..star], &sentence[star + 1..]);
+    let checksum = u8::from_str_radix(checksum, 16).map_err(|_| Error::Checksum)?;
+    if data.bytes().fold(0, |sum, byte| sum ^ byte) != checksum {
+        return Err(Error::Checksum);
+    }
+
+    let mut fields = data.split(',');
+    let address = fields.next().ok_or(Error::Syntax)?;
+    if address.len() != 5 {
+        return Err(Error::Syntax);
+    }
+    // the field following the next `skip` ones
+    let mut field = move |skip| fields.nth(skip).ok_or(Error::Syntax);
+
+    match &address[2..] {
+        // $GPRMC,hhmmss.ss,A,llll.ll,a,yyyyy.yy,a,x.x,x.x,ddmmyy,...
+        "RMC" => {
+            let time = field(0)?;
+            if field(0)? != "A" {
+                return Err(Error::NoFix);
+            }
+            let date = field(6)?;
+            if time.is_empty() || date.is_empty() {
+                return Err(Error::NoFix);
+            }
+            if date.len() != 6 {
+                return Err(Error::Syntax);
+            }
+            let date = NaiveDate::from_ymd_opt(
+                2000 + number(&date[4..6])? as i32,
+                number(&date[2..4])?,
+                number(&date[0..2])?,
+            )
+            .ok_or(Error::InvalidTime)?;
+            Ok(Some((Sentence::Rmc, date.and_time(parse_time(time)?))))
+        }
+        // $GPZDA,hhmmss.ss,dd,mm,yyyy,zh,zm
+        "ZDA" => {
+            let (time, day, month, year) = (field(0)?, field(0)?, field(0)?, field(0)?);
+            if [time, day, month, year]
+                .iter()
+                .any(|field| field.is_empty())
+            {
+                return Err(Error::NoFix);
+            }
+            let date = NaiveDate::from_ymd_opt(number(year)? as i32, number(month)?, number(day)?)
+                .ok_or(Error::InvalidTime)?;
+            Ok(Some((Sentence::Zda, date.and_time(parse_time(time)?))))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Decimal number consisting of digits only
+fn number(field: &str) -> Result<u32, Error> {
+    if field.is_empty() || !field.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(Error::Syntax);
+    }
+    field.parse().map_err(|_| Error::Syntax)
+}
+
+/// Time of the form `hhmmss` with optional fractional seconds
+fn parse_time(field: &str) -> Result<NaiveTime, Error> {
+    let (time, fraction) = match field.find('.') {
+        Some(dot) => (&field[..dot], &field[dot + 1..]),
+        None => (field, ""),
+    };
+    if time.len() != 6 {
+        return Err(Error::Syntax);
+    }
+
+    // milliseconds from the first three fractional digits
+    let milli = if fraction.is_empty() {
+        0
+    } else {
+        let digits = &fraction[..fraction.len().min(3)];
+        number(digits)? * [100, 10, 1][digits.len() - 1]
+    };
+    // a leap second is represented as an overlong second 59
+    let (second, milli) = match number(&time[4..6])? {
+        60 => (59, milli + 1000),
+        second => (second, milli),
+    };
+    NaiveTime::from_hms_milli_opt(number(&time[0..2])?, number(&time[2..4])?, second, milli)
+        .ok_or(Error::InvalidTime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RMC: &str = "$GNRMC,083559.00,A,4717.11437,N,00833.91522,E,0.004,77.52,091202,,,A*49";
+    const RMC_NO_FIX: &str = "$GPRMC,083559.00,V,,,,,,,091202,,,N*77";
+    const RMC_EMPTY: &str = "$GPRMC,,V,,,,,,,,,,N*53";
+    const ZDA: &str = "$GPZDA,082710.00,16,09,2002,00,00*64";
+    const ZDA_EMPTY: &str = "$GPZDA,,,,,00,00*48";
+    const ZDA_LEAP_SECOND: &str = "$GPZDA,235960.00,31,12,2016,00,00*69";
+    const GGA: &str = "$GPGGA,092725.00,4717.11399,N,00833.91590,E,1,08,1.01,499.6,M,48.0,M,,*5B";
+
+    /// Time parsed from the sentence `line` with its leading `$`
+    fn parse_line(line: &str) -> Result<Option<NaiveDateTime>, Error> {
+        parse(&line.as_bytes()[1..]).map(|time| time.map(|(_, time)| time))
+    }
+
+    fn time(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(year, month, day).and_hms(hour, minute, second)
+    }
+
+    #[test]
+    fn sentences() {
+        assert_eq!(parse_line(RMC), Ok(Some(time(2002, 12, 9, 8, 35, 59))));
+        assert_eq!(parse_line(ZDA), Ok(Some(time(2002, 9, 16, 8, 27, 10))));
+        assert_eq!(parse_line(GGA), Ok(None));
+        assert_eq!(
+            parse(&RMC.as_bytes()[1..]).map(|time| time.map(|(sentence, _)| sentence)),
+            Ok(Some(Sentence::Rmc))
+        );
+        assert_eq!(
+            parse(&ZDA.as_bytes()[1..]).map(|time| time.map(|(sentence, _)| sentence)),
+            Ok(Some(Sentence::Zda))
+        );
+        assert_eq!(
+            parse_line(ZDA_LEAP_SECOND),
+            Ok(Some(
+                NaiveDate::from_ymd(2016, 12, 31).and_hms_milli(23, 59, 59, 1000)
+            ))
+        );
+        assert_eq!(
+            parse_line("$GPZDA,120000.50,16,09,2002,00,00*6E"),
+            Ok(Some(
+                NaiveDate::from_ymd(2002, 9, 16).and_hms_milli(12, 0, 0, 500)
+            ))
+        );
+    }
+
+    #[test]
+    fn no_fix() {
+        assert_eq!(parse_line(RMC_NO_FIX), Err(Error::NoFix));
+        assert_eq!(parse_line(RMC_EMPTY), Err(Error::NoFix));
+        assert_eq!(parse_line(ZDA_EMPTY), Err(Error::NoFix));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_line("$GPZDA,082710.00,16,09,2002,00,00*65"),
+            Err(Error::Checksum)
+        );
+        assert_eq!(
+            parse_line("$GPZDA,082710.00,16,09,2003,00,00*64"),
+            Err(Error::Checksum)
+        );
+        assert_eq!(
+            parse_line("$GPZDA,082710.00,16,09,2002,00,00"),
+            Err(Error::Checksum)
+        );
+        // 31 February
+        assert_eq!(
+            parse_line("$GPRMC,083559.00,A,4717.11437,N,00833.91522,E,0.004,77.52,310202,,,A*5D"),
+            Err(Error::InvalidTime)
+        );
+        assert_eq!(parse(b"GPZDA\xff*00"), Err(Error::Syntax));
+    }
+
+    #[test]
+    fn parser() {
+        let mut parser = NmeaParser::new();
+        let mut push = |bytes: &str| {
+            bytes
+                .bytes()
+                .map(|byte| parser.push(byte))
+                .filter(|result| *result != Err(WouldBlock))
+                .collect::<std::vec::Vec<_>>()
+        };
+
+        // bytes before the first `$` and a sentence cut short are dropped
+        assert_eq!(push("0.00,A,4717*5D\r\n"), []);
+        assert_eq!(push("$GPRMC,0835$GPZDA"), []);
+        assert_eq!(push(&ZDA[6..]), []);
+        assert_eq!(
+            push("\r\n"),
+            [Ok((Sentence::Zda, time(2002, 9, 16, 8, 27, 10)))]
+        );
+        assert_eq!(push(GGA), []);
+        assert_eq!(push("\r\n"), []);
+        assert_eq!(push(RMC_NO_FIX), []);
+        assert_eq!(push("\r\n"), [Err(Other(Error::NoFix))]);
+
+        // longer than the 82 characters allowed
+        let long = std::format!("${:0<90}", "GPTXT,");
+        assert_eq!(push(&long), [Err(Other(Error::Overflow))]);
+        assert_eq!(push("\r\n"), []);
+    }
+}
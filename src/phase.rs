@@ -0,0 +1,306 @@
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+
+/// Maximum deviation of a second marker from the expected phase
+const TOLERANCE_MS: u32 = 60;
+/// Consecutive second markers in phase required to lock
+const LOCK_MARKERS: u8 = 3;
+/// Missing second markers bridged before the lock is lost
+const MAX_MISSING: u32 = 10;
+
+/// Tracking of the 1Hz second markers of a time signal
+///
+/// Once locked onto the second markers the tracker keeps counting the seconds
+/// through missing or disturbed pulses. After a minute marker the second
+/// within the minute is known, and if the minute has been confirmed by a
+/// complete frame once it is carried on without further frames.
+pub struct PhaseTracker {
+    /// time since the last second marker
+    elapsed_ms: u32,
+    markers: u8,
+    second: Option<u32>,
+    minute: Option<NaiveDateTime>,
+    /// a leap second has been announced
+    leap_second: bool,
+}
+
+impl Default for PhaseTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhaseTracker {
+    pub fn new() -> Self {
+        Self {
+            elapsed_ms: 0,
+            markers: 0,
+            second: None,
+            minute: None,
+            leap_second: false,
+        }
+    }
+
+    pub fn locked(&self) -> bool {
+        self.markers >= LOCK_MARKERS
+    }
+
+    /// Second within the current minute
+    pub fn second(&self) -> Option<u32> {
+        self.second
+    }
+
+    /// Time of the current second, if both second and minute are known
+    pub fn now(&self) -> Option<NaiveDateTime> {
+        match (self.minute?, self.second?) {
+            // chrono represents a leap second as an overlong second 59
+            (minute, 60) => (minute + Duration::seconds(59)).with_nanosecond(1_000_000_000),
+            (minute, second) => Some(minute + Duration::seconds(second.into())),
+        }
+    }
+
+    /// Whether the current minute ends with a leap second
+    ///
+    /// Leap seconds are inserted at the end of the last minute of a month in
+    /// UTC, once they have been announced.
+    pub fn leap_second_due(&self) -> bool {
+        match (self.leap_second, self.minute) {
+            (true, Some(minute)) => {
+                minute.hour() == 23
+                    && minute.minute() == 59
+                    && (minute + Duration::days(1)).day() == 1
+            }
+            _ => false,
+        }
+    }
+
+    /// Set whether the time signal announces a leap second
+    pub fn announce_leap_second(&mut self, announced: bool) {
+        self.leap_second = announced;
+    }
+
+    /// Add an edge `delta_ms` after the previous one
+    ///
+    /// `candidate` tells whether the edge may start a second at all, i.e. is
+    /// a falling edge. Returns whether the edge is a second marker in phase
+    /// with the locked one.
+    pub fn edge(&mut self, candidate: bool, delta_ms: u32) -> bool {
+        self.elapsed_ms = self.elapsed_ms.saturating_add(delta_ms);
+        if !candidate {
+            return false;
+        }
+
+        let seconds = (self.elapsed_ms + 500) / 1000;
+        let deviation = (self.elapsed_ms as i32 - (seconds * 1000) as i32).unsigned_abs();
+        let in_phase = seconds > 0 && seconds <= MAX_MISSING + 1 && deviation <= TOLERANCE_MS;
+
+        if !in_phase {
+            if self.locked() && self.elapsed_ms <= (MAX_MISSING + 1) * 1000 {
+                // most likely a disturbance, keep waiting for the next marker
+                return false;
+            }
+            // start over with this edge as the first marker
+            self.elapsed_ms = 0;
+            self.markers = 1;
+            self.second = None;
+            self.minute = None;
+            return false;
+        }
+
+        self.elapsed_ms = 0;
+        self.markers = (self.markers + 1).min(LOCK_MARKERS);
+        if let Some(second) = self.second {
+            let length = if self.leap_second_due() { 61 } else { 60 };
+            let second = second + seconds;
+            if second >= length {
+                self.minute = self.minute.map(|minute| minute + Duration::minutes(1));
+            }
+            self.second = Some(second % length);
+        }
+
+        self.locked()
+    }
+
+    /// The last second marker started `second` of a minute
+    ///
+    /// `second` may be past the end of the minute, for signals recognising
+    /// the start of a minute only after its first second. `time` is the start
+    /// of the minute if it has been confirmed. Without
+    /// it a minute contradicting the counted seconds is ignored, as a missing
+    /// pulse looks just like a minute marker.
+    pub fn minute_marker(&mut self, second: u32, time: Option<NaiveDateTime>) {
+        if !self.locked() {
+            return;
+        }
+
+        match (time, self.second) {
+            (Some(time), _) => {
+                self.second = Some(second);
+                self.minute = Some(time);
+            }
+            (None, None) => self.second = Some(second),
+            (None, Some(_)) => (),
+        }
+
+        // seconds past the end of the minute belong to the next one
+        let length = if self.leap_second_due() { 61 } else { 60 };
+        if let Some(second) = self.second.filter(|&second| second >= length) {
+            self.second = Some(second - length);
+            self.minute = self.minute.map(|minute| minute + Duration::minutes(1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Tracker locked onto the second markers, with a rising edge in between
+    fn locked() -> PhaseTracker {
+        let mut phase = PhaseTracker::new();
+        for _ in 0..LOCK_MARKERS {
+            phase.edge(false, 100);
+            phase.edge(true, 900);
+        }
+        phase
+    }
+
+    fn minute() -> NaiveDateTime {
+        NaiveDate::from_ymd(2024, 2, 28).and_hms(12, 37, 0)
+    }
+
+    #[test]
+    fn lock() {
+        let mut phase = PhaseTracker::new();
+        assert!(!phase.edge(true, 1000));
+        assert!(!phase.edge(true, 1000));
+        assert!(!phase.locked());
+        // rising edges do not mark a second
+        assert!(!phase.edge(false, 940));
+        assert!(phase.edge(true, 120));
+        assert!(phase.locked());
+        // the second is only known after a minute marker
+        assert_eq!(phase.second(), None);
+        assert_eq!(phase.now(), None);
+    }
+
+    #[test]
+    fn no_lock_out_of_phase() {
+        let mut phase = PhaseTracker::new();
+        phase.edge(true, 1000);
+        phase.edge(true, 1000);
+        // too far off, starts over
+        assert!(!phase.edge(true, 1070));
+        assert!(!phase.edge(true, 1000));
+        assert!(!phase.locked());
+        assert!(phase.edge(true, 1000));
+
+        // a minute marker before the lock is ignored
+        let mut phase = PhaseTracker::new();
+        phase.edge(true, 1000);
+        phase.minute_marker(0, Some(minute()));
+        assert_eq!(phase.second(), None);
+    }
+
+    #[test]
+    fn count_seconds() {
+        let mut phase = locked();
+        phase.minute_marker(0, None);
+        assert_eq!(phase.second(), Some(0));
+        for second in 1..60 {
+            assert!(phase.edge(true, 1000));
+            assert_eq!(phase.second(), Some(second));
+        }
+        assert!(phase.edge(true, 1000));
+        assert_eq!(phase.second(), Some(0));
+        // the time is still unknown
+        assert_eq!(phase.now(), None);
+    }
+
+    #[test]
+    fn flywheel() {
+        let mut phase = locked();
+        phase.minute_marker(0, Some(minute()));
+        assert_eq!(phase.now(), Some(minute()));
+
+        // three pulses missing
+        assert!(phase.edge(true, 4000));
+        assert_eq!(phase.second(), Some(4));
+        // a disturbance in between two markers
+        assert!(!phase.edge(true, 300));
+        assert!(!phase.edge(false, 200));
+        assert!(phase.edge(true, 500));
+        assert_eq!(phase.second(), Some(5));
+        assert!(phase.locked());
+
+        // on into the next minute without a marker
+        for _ in 5..60 {
+            assert!(phase.edge(true, 1000));
+        }
+        assert_eq!(phase.second(), Some(0));
+        assert_eq!(phase.now(), Some(minute() + Duration::minutes(1)));
+        assert!(phase.edge(true, 1020));
+        assert_eq!(phase.now(), Some(minute() + Duration::seconds(61)));
+    }
+
+    #[test]
+    fn lost() {
+        let mut phase = locked();
+        phase.minute_marker(0, Some(minute()));
+        assert!(phase.edge(true, (MAX_MISSING + 1) * 1000));
+        assert_eq!(phase.second(), Some(MAX_MISSING + 1));
+        // one more missing pulse loses the lock
+        assert!(!phase.edge(true, (MAX_MISSING + 2) * 1000));
+        assert!(!phase.locked());
+        assert_eq!(phase.second(), None);
+        assert_eq!(phase.now(), None);
+    }
+
+    #[test]
+    fn minute_marker() {
+        let mut phase = locked();
+        phase.minute_marker(0, None);
+        phase.edge(true, 1000);
+        phase.edge(true, 1000);
+        // an unconfirmed marker contradicting the count is a missing pulse
+        phase.minute_marker(0, None);
+        assert_eq!(phase.second(), Some(2));
+        // a confirmed one wins
+        phase.minute_marker(0, Some(minute()));
+        assert_eq!(phase.now(), Some(minute()));
+        // seconds past the end of the minute
+        phase.minute_marker(61, Some(minute()));
+        assert_eq!(phase.second(), Some(1));
+        assert_eq!(
+            phase.now(),
+            Some(minute() + Duration::minutes(1) + Duration::seconds(1))
+        );
+    }
+
+    #[test]
+    fn leap_second() {
+        let last = NaiveDate::from_ymd(2016, 12, 31).and_hms(23, 59, 0);
+        let mut phase = locked();
+        phase.announce_leap_second(true);
+        phase.minute_marker(0, Some(last - Duration::minutes(1)));
+        assert!(!phase.leap_second_due());
+        phase.minute_marker(0, Some(last));
+        assert!(phase.leap_second_due());
+
+        for _ in 0..60 {
+            assert!(phase.edge(true, 1000));
+        }
+        assert_eq!(phase.second(), Some(60));
+        assert_eq!(
+            phase.now(),
+            last.with_second(59)
+                .and_then(|time| time.with_nanosecond(1_000_000_000))
+        );
+        assert!(phase.edge(true, 1000));
+        assert_eq!(phase.second(), Some(0));
+        assert_eq!(
+            phase.now(),
+            Some(NaiveDate::from_ymd(2017, 1, 1).and_hms(0, 0, 0))
+        );
+    }
+}
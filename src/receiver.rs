@@ -0,0 +1,49 @@
+use embedded_hal::digital::v2::InputPin;
+use stm32f0xx_hal::{
+    counter::Counter,
+    gpio::{Input, Pin, PullUp},
+};
+
+/// Edge of the receiver output as captured in the interrupt
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timestamp {
+    pub rising: bool,
+    /// milliseconds since the start, wrapping around
+    pub time_ms: u32,
+}
+
+/// Time signal receiver connected to an interrupt capable pin
+///
+/// The timer keeps a millisecond clock to timestamp the edges of the signal.
+pub struct Receiver<Timer: Counter> {
+    timer: Timer,
+    pin: Pin<Input<PullUp>>,
+    /// the receiver pulls the output low during a pulse
+    inverted: bool,
+    time_ms: u32,
+}
+
+impl<Timer: Counter> Receiver<Timer> {
+    pub fn init(timer: Timer, pin: Pin<Input<PullUp>>, inverted: bool) -> Self {
+        Self {
+            timer,
+            pin,
+            inverted,
+            time_ms: 0,
+        }
+    }
+
+    /// Milliseconds since the start, wrapping around
+    pub fn now_ms(&mut self) -> u32 {
+        self.time_ms = self.time_ms.wrapping_add(self.timer.restart());
+        self.time_ms
+    }
+
+    /// Read the edge which just triggered the interrupt
+    pub fn edge(&mut self) -> Timestamp {
+        Timestamp {
+            rising: self.pin.is_high().unwrap() ^ self.inverted,
+            time_ms: self.now_ms(),
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use crate::confidence::{Confidence, Plausibility};
+use crate::dcf77::{DCF77Frame, Error};
+use crate::filter::EdgeFilter;
+use crate::phase::PhaseTracker;
+use crate::stats::ReceptionStats;
+use chrono::{Duration, NaiveDateTime};
+use core::marker::PhantomData;
+use heapless::{consts::*, Vec};
+use nb::Error::WouldBlock;
+
+/// Pulses shorter than this are ignored
+const GLITCH_MS: u32 = 30;
+/// Minimum time between the starts of two seconds before the phase is locked
+const MIN_SECOND_MS: u32 = 900;
+
+/// Decoder of a radio time signal
+///
+/// The receiver output has to be connected such that the pulse of every second
+/// starts with a falling edge.
+pub trait TimeSignal {
+    /// Add an edge of the signal `delta_ms` after the previous one
+    fn update(&mut self, rising_edge: bool, delta_ms: u32);
+
+    /// The last edge marked the start of a second
+    fn second_started(&self) -> bool;
+
+    /// Time of the current second in UTC
+    fn now(&self) -> nb::Result<NaiveDateTime, Error>;
+
+    /// Whether the current minute ends with a leap second
+    fn leap_second(&self) -> bool;
+
+    fn confidence(&self) -> Confidence;
+
+    fn stats(&self) -> &ReceptionStats;
+}
+
+/// Pulses of a second, as start and width relative to the start of the second
+pub type Pulses = Vec<(u32, u32), U2>;
+
+/// Symbol of stations transmitting one bit or a marker per second
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    Zero,
+    One,
+    Marker,
+}
+
+/// A time signal transmitting one symbol per second
+pub trait Station {
+    type Symbol: Copy + PartialEq;
+
+    /// Symbol transmitted by the pulses of a second, `None` if unreadable
+    fn symbol(pulses: &[(u32, u32)]) -> Option<Self::Symbol>;
+
+    /// Whether the second with `symbol` following `previous` is the first one
+    /// of a minute
+    fn minute_start(previous: Option<Self::Symbol>, symbol: Option<Self::Symbol>) -> bool;
+
+    /// Decode the seconds of a complete minute
+    ///
+    /// `previous` is the frame decoded for the minute before, if any. Returns
+    /// the frame describing the minute starting now.
+    fn decode(
+        symbols: &[Option<Self::Symbol>],
+        previous: Option<&DCF77Frame>,
+    ) -> Result<DCF77Frame, Error>;
+
+    /// Offset of the time transmitted in `frame` to UTC
+    fn utc_offset(frame: &DCF77Frame) -> Duration;
+}
+
+/// Whether a pulse of `width_ms` matches `nominal_ms`
+pub fn matches(width_ms: u32, nominal_ms: u32, tolerance_ms: u32) -> bool {
+    (width_ms as i32 - nominal_ms as i32).unsigned_abs() <= tolerance_ms
+}
+
+/// Number transmitted as BCD, as first bit and the weights of the bits
+///
+/// Bits with a weight of zero are skipped.
+pub type Field = (usize, &'static [u32]);
+
+/// Decimal digit a bit of `weight` belongs to
+fn digit(weight: u32) -> usize {
+    match weight {
+        w if w < 10 => 0,
+        w if w < 100 => 1,
+        _ => 2,
+    }
+}
+
+/// Number encoded by `bits` in `field`
+///
+/// Every decimal digit of the number has to be valid BCD.
+pub fn weighted(bits: u64, (first, weights): Field) -> Result<u32, Error> {
+    let mut digits = [0u32; 3];
+    for (i, &weight) in weights.iter().enumerate() {
+        if (bits >> (first + i)) & 1 == 1 {
+            digits[digit(weight)] += weight;
+        }
+    }
+
+    if digits
+        .iter()
+        .zip([1, 10, 100].iter())
+        .any(|(digit, scale)| digit / scale > 9)
+    {
+        return Err(Error::InvalidBCD);
+    }
+    Ok(digits.iter().sum())
+}
+
+/// Bits of a minute with markers at the seconds in `markers`
+///
+/// Markers have to be exactly at these seconds. A leap second is transmitted
+/// as an additional marker at the end of the minute.
+pub fn marker_bits(symbols: &[Option<Symbol>], markers: &[usize]) -> Result<u64, Error> {
+    let symbols = match symbols {
+        [minute @ .., Some(Symbol::Marker)] if minute.len() == 60 => minute,
+        _ if symbols.len() == 60 => symbols,
+        _ => return Err(Error::ProtocolError),
+    };
+
+    let mut bits = 0u64;
+    for (second, symbol) in symbols.iter().enumerate() {
+        match (symbol, markers.contains(&second)) {
+            (Some(Symbol::Marker), true) => (),
+            (Some(Symbol::Zero), false) => (),
+            (Some(Symbol::One), false) => bits |= 1 << second,
+            (None, _) => return Err(Error::UnreadableBit),
+            _ => return Err(Error::StartOfMinuteError),
+        }
+    }
+    Ok(bits)
+}
+
+/// Set the bits of `value` in `field`
+pub fn insert_weighted(bits: &mut u64, (first, weights): Field, value: u32) {
+    let mut rest = [value % 10, value / 10 % 10 * 10, value / 100 * 100];
+    for (i, &weight) in weights.iter().enumerate() {
+        let rest = &mut rest[digit(weight)];
+        if weight > 0 && *rest >= weight {
+            *rest -= weight;
+            *bits |= 1 << (first + i);
+        }
+    }
+}
+
+/// Decoder for stations transmitting a symbol every second
+///
+/// Missing seconds are filled in as unreadable, so the position within the
+/// minute is kept.
+pub struct Decoder<S: Station> {
+    filter: EdgeFilter,
+    phase: PhaseTracker,
+    plausibility: Plausibility,
+    stats: ReceptionStats,
+    /// the last input edge started a second in phase
+    second_started: bool,
+    /// the start of a second has been seen
+    in_second: bool,
+    /// time since the start of the current second
+    since_second_ms: u32,
+    /// start of the pulse in progress within the current second
+    pulse_start: Option<u32>,
+    pulses: Pulses,
+    previous: Option<S::Symbol>,
+    /// seconds of the current minute, if its start has been seen
+    symbols: Option<Vec<Option<S::Symbol>, U64>>,
+    /// the last complete frame
+    frame: Result<DCF77Frame, Error>,
+    station: PhantomData<S>,
+}
+
+impl<S: Station> Decoder<S> {
+    /// `required_frames` consecutive frames have to match before a time is
+    /// returned by `now()`
+    pub fn init(required_frames: u8) -> Self {
+        Self {
+            filter: EdgeFilter::new(GLITCH_MS),
+            phase: PhaseTracker::new(),
+            plausibility: Plausibility::new(required_frames),
+            stats: ReceptionStats::default(),
+            second_started: false,
+            in_second: false,
+            since_second_ms: 0,
+            pulse_start: None,
+            pulses: Vec::new(),
+            previous: None,
+            symbols: None,
+            frame: Err(Error::StartNotFound),
+            station: PhantomData,
+        }
+    }
+
+    pub fn phase(&self) -> &PhaseTracker {
+        &self.phase
+    }
+
+    /// The last complete frame, describing the current minute
+    pub fn frame(&self) -> Result<DCF77Frame, Error> {
+        self.frame
+    }
+
+    /// Finish the current second, followed by `missing` seconds without pulse
+    ///
+    /// Returns the second of the minute now started, if known.
+    fn end_second(&mut self, missing: u32) -> Option<u32> {
+        let symbol = S::symbol(&self.pulses);
+        self.pulses = Pulses::new();
+        self.stats.symbol(symbol.is_some());
+
+        // the leap second follows the marker of the last second
+        let leap_second = self.phase.leap_second_due()
+            && self.symbols.as_ref().map(|symbols| symbols.len()) == Some(60);
+
+        if S::minute_start(self.previous, symbol) && !leap_second {
+            if let Some(symbols) = &self.symbols {
+                self.frame = S::decode(symbols, self.frame.as_ref().ok());
+                self.stats.frame(self.frame);
+                self.plausibility.update(
+                    self.frame
+                        .and_then(|frame| Ok(frame.datetime(0)? - S::utc_offset(&frame))),
+                );
+                if let (Some(_), Ok(frame)) = (self.plausibility.confirmed(), self.frame) {
+                    self.phase
+                        .announce_leap_second(frame.leap_second_announcement);
+                }
+            }
+            self.symbols = Some(Vec::new());
+        }
+        self.previous = symbol;
+
+        let symbols = self.symbols.as_mut()?;
+        let mut seconds = core::iter::once(symbol).chain((0..missing).map(|_| None));
+        if seconds.try_for_each(|second| symbols.push(second)).is_err() {
+            // no minute marker for too long, the minute it would have
+            // confirmed is lost as well
+            self.symbols = None;
+            self.plausibility.reset();
+            return None;
+        }
+        Some(symbols.len() as u32)
+    }
+}
+
+impl<S: Station> TimeSignal for Decoder<S> {
+    fn update(&mut self, rising_edge: bool, delta_ms: u32) {
+        self.stats.edge(delta_ms);
+        self.second_started = false;
+        for edge in self.filter.update(rising_edge, delta_ms).iter().flatten() {
+            self.since_second_ms = self.since_second_ms.saturating_add(edge.delta_ms);
+
+            let candidate = !edge.rising && self.since_second_ms >= MIN_SECOND_MS;
+            let marker = self.phase.edge(candidate, edge.delta_ms);
+            let second_start = if self.phase.locked() {
+                marker
+            } else {
+                candidate
+            };
+
+            if second_start {
+                if self.in_second {
+                    let missing = ((self.since_second_ms + 500) / 1000).max(1) - 1;
+                    let second = self.end_second(missing);
+                    if let (true, Some(second)) = (marker, second) {
+                        self.phase
+                            .minute_marker(second, self.plausibility.confirmed());
+                    }
+                }
+                self.in_second = true;
+                self.since_second_ms = 0;
+                self.pulse_start = Some(0);
+            } else if edge.rising {
+                if let Some(start) = self.pulse_start.take() {
+                    let width = self.since_second_ms - start;
+                    // spikes passed on by the edge filter
+                    if width >= GLITCH_MS {
+                        self.pulses.push((start, width)).ok();
+                    }
+                }
+            } else if self.in_second {
+                self.pulse_start = Some(self.since_second_ms);
+            }
+            self.second_started = marker;
+        }
+    }
+
+    fn second_started(&self) -> bool {
+        self.second_started
+    }
+
+    fn now(&self) -> nb::Result<NaiveDateTime, Error> {
+        self.phase.now().ok_or(WouldBlock)
+    }
+
+    fn leap_second(&self) -> bool {
+        self.phase.leap_second_due()
+    }
+
+    fn confidence(&self) -> Confidence {
+        self.plausibility.confidence()
+    }
+
+    fn stats(&self) -> &ReceptionStats {
+        &self.stats
+    }
+}
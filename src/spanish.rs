@@ -0,0 +1,160 @@
+//! Spanish phrasing, with the minutes following the hour
+//!
+//! One o'clock is singular, "ES LA UNA", all other hours are plural as in
+//! "SON LAS DOS".
+
+use crate::face::{Face, HourName, Phrase, Phrasing};
+use bitflags::bitflags;
+use chrono::NaiveTime;
+
+bitflags! {
+    pub struct MainWord : u32 {
+        const ES = 0x01;
+        const LA = 0x02;
+        const SON = 0x04;
+        const LAS = 0x08;
+        const Y = 0x10;
+        const MENOS = 0x20;
+        const CUARTO = 0x40;
+        const MEDIA = 0x80;
+        const CINCO = 0x100;
+        const DIEZ = 0x200;
+        const VEINTE = 0x400;
+        const VEINTICINCO = 0x800;
+    }
+}
+
+/// Minutes following the hour
+const FIVE_MINUTE_STATE: [Phrase; 12] = [
+    Phrase {
+        main: 0,
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::Y.bits() | MainWord::CINCO.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::Y.bits() | MainWord::DIEZ.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::Y.bits() | MainWord::CUARTO.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::Y.bits() | MainWord::VEINTE.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::Y.bits() | MainWord::VEINTICINCO.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::Y.bits() | MainWord::MEDIA.bits(),
+        hour_offset: 0,
+    },
+    Phrase {
+        main: MainWord::MENOS.bits() | MainWord::VEINTICINCO.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::MENOS.bits() | MainWord::VEINTE.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::MENOS.bits() | MainWord::CUARTO.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::MENOS.bits() | MainWord::DIEZ.bits(),
+        hour_offset: 1,
+    },
+    Phrase {
+        main: MainWord::MENOS.bits() | MainWord::CINCO.bits(),
+        hour_offset: 1,
+    },
+];
+
+const ES_LA: HourName = HourName {
+    main: MainWord::ES.bits() | MainWord::LA.bits(),
+    number: true,
+    replace: None,
+};
+
+const PHRASING: Phrasing = Phrasing {
+    steps: &FIVE_MINUTE_STATE,
+    full_hour: false,
+    hour: HourName {
+        main: MainWord::SON.bits() | MainWord::LAS.bits(),
+        number: true,
+        replace: None,
+    },
+    hours: &[(1, ES_LA), (13, ES_LA)],
+};
+
+pub fn face(time: NaiveTime) -> Face {
+    PHRASING.face(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::tests::{check_day, phrase, HOUR};
+
+    const HOURS: [&str; 12] = [
+        "doce", "una", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve", "diez",
+        "once",
+    ];
+
+    fn words(face: &Face) -> String {
+        let words = [
+            (MainWord::ES.bits(), "es"),
+            (MainWord::LA.bits(), "la"),
+            (MainWord::SON.bits(), "son"),
+            (MainWord::LAS.bits(), "las"),
+            (HOUR, ""),
+            (MainWord::Y.bits(), "y"),
+            (MainWord::MENOS.bits(), "menos"),
+            (MainWord::CUARTO.bits(), "cuarto"),
+            (MainWord::MEDIA.bits(), "media"),
+            (MainWord::CINCO.bits(), "cinco"),
+            (MainWord::DIEZ.bits(), "diez"),
+            (MainWord::VEINTE.bits(), "veinte"),
+            (MainWord::VEINTICINCO.bits(), "veinticinco"),
+        ];
+        // "en punto" is not shown
+        phrase(face, &words, &HOURS, "en punto")
+    }
+
+    /// Name of `hour` from 0 to 23 with its article
+    fn name(hour: usize) -> String {
+        match hour % 12 {
+            1 => "es la una".into(),
+            hour => std::format!("son las {}", HOURS[hour]),
+        }
+    }
+
+    #[test]
+    fn five_minute_steps() {
+        let steps = [
+            "{}",
+            "{} y cinco",
+            "{} y diez",
+            "{} y cuarto",
+            "{} y veinte",
+            "{} y veinticinco",
+            "{} y media",
+            "{+} menos veinticinco",
+            "{+} menos veinte",
+            "{+} menos cuarto",
+            "{+} menos diez",
+            "{+} menos cinco",
+        ];
+        check_day(face, words, |hour, step| {
+            steps[step]
+                .replace("{}", &name(hour))
+                .replace("{+}", &name(hour + 1))
+        });
+    }
+}
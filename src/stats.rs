@@ -0,0 +1,204 @@
+use crate::classifier::Pulse;
+use crate::dcf77::Error;
+
+/// Fractional bits of the quality score
+const FRACTION: u32 = 8;
+/// Inverse weight of a single pulse in the quality score
+const QUALITY_WEIGHT: i32 = 16;
+
+/// Reception statistics of a time signal decoder
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ReceptionStats {
+    /// edges seen on the input
+    pub edges: u32,
+    /// pulses decoded as a bit
+    pub valid_bits: u32,
+    /// pulses not matching any bit
+    pub invalid_pulses: u32,
+    /// frames received completely
+    pub frames: u32,
+    /// frames passing all checks
+    pub good_frames: u32,
+    /// good frames after repairing them against the expected frame
+    pub corrected_frames: u32,
+    pub parity_errors_minute: u32,
+    pub parity_errors_hour: u32,
+    pub parity_errors_date: u32,
+    /// frames with a wrong length, wrong marker bits or an invalid time
+    pub structural_errors: u32,
+    /// time since the last good frame, `None` before the first one
+    pub since_good_frame_ms: Option<u32>,
+    /// rolling share of valid pulses, fixed point with `FRACTION` bits
+    quality: u16,
+}
+
+impl ReceptionStats {
+    /// Rolling signal quality from 0 (nothing decodable) to 100
+    pub fn quality(&self) -> u8 {
+        (self.quality >> FRACTION) as u8
+    }
+
+    pub fn edge(&mut self, delta_ms: u32) {
+        self.edges = self.edges.wrapping_add(1);
+        self.since_good_frame_ms = self
+            .since_good_frame_ms
+            .map(|ms| ms.saturating_add(delta_ms));
+    }
+
+    pub fn pulse(&mut self, pulse: Pulse) {
+        match pulse {
+            Pulse::Zero | Pulse::One => self.symbol(true),
+            Pulse::Invalid => self.symbol(false),
+            // already accounted for by the edge filter
+            Pulse::Glitch => (),
+        }
+    }
+
+    /// A second has been decoded into a bit or marker, or not
+    pub fn symbol(&mut self, valid: bool) {
+        let sample = if valid {
+            self.valid_bits = self.valid_bits.wrapping_add(1);
+            100
+        } else {
+            self.invalid_pulses = self.invalid_pulses.wrapping_add(1);
+            0
+        };
+
+        // round away from zero, so the score reaches both bounds
+        let quality = self.quality as i32;
+        let step = (sample << FRACTION) - quality;
+        let step = (step + step.signum() * (QUALITY_WEIGHT - 1)) / QUALITY_WEIGHT;
+        self.quality = (quality + step) as u16;
+    }
+
+    pub fn frame<T>(&mut self, result: Result<T, Error>) {
+        self.frames = self.frames.wrapping_add(1);
+
+        let counter = match result {
+            Ok(_) => {
+                self.since_good_frame_ms = Some(0);
+                &mut self.good_frames
+            }
+            Err(Error::ParityErrorMinute) => &mut self.parity_errors_minute,
+            Err(Error::ParityErrorHour) => &mut self.parity_errors_hour,
+            Err(Error::ParityErrorDate) => &mut self.parity_errors_date,
+            Err(_) => &mut self.structural_errors,
+        };
+        *counter = counter.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames() {
+        let errors = [
+            Error::StartNotFound,
+            Error::StateChangeError,
+            Error::ProtocolError,
+            Error::InvalidTime,
+            Error::InvalidDate,
+            Error::StartOfMinuteError,
+            Error::StartOfTimeError,
+            Error::TimezoneError,
+            Error::InvalidBCD,
+            Error::InvalidMinute,
+            Error::InvalidHour,
+            Error::InvalidDay,
+            Error::InvalidMonth,
+            Error::InvalidWeekday,
+            Error::UnreadableBit,
+        ];
+        let mut stats = ReceptionStats::default();
+        for &error in errors.iter() {
+            stats.frame::<()>(Err(error));
+        }
+        assert_eq!(stats.structural_errors, errors.len() as u32);
+        assert_eq!(stats.frames, errors.len() as u32);
+
+        stats.frame::<()>(Err(Error::ParityErrorMinute));
+        assert_eq!(stats.parity_errors_minute, 1);
+        stats.frame::<()>(Err(Error::ParityErrorHour));
+        stats.frame::<()>(Err(Error::ParityErrorHour));
+        assert_eq!(stats.parity_errors_hour, 2);
+        stats.frame::<()>(Err(Error::ParityErrorDate));
+        assert_eq!(stats.parity_errors_date, 1);
+        assert_eq!(stats.structural_errors, errors.len() as u32);
+        assert_eq!(stats.good_frames, 0);
+        assert_eq!(stats.frames, errors.len() as u32 + 4);
+    }
+
+    #[test]
+    fn since_good_frame() {
+        let mut stats = ReceptionStats::default();
+        stats.edge(1000);
+        assert_eq!(stats.since_good_frame_ms, None);
+        stats.frame(Ok(()));
+        assert_eq!((stats.good_frames, stats.frames), (1, 1));
+        assert_eq!(stats.since_good_frame_ms, Some(0));
+        stats.edge(1000);
+        stats.edge(200);
+        stats.frame::<()>(Err(Error::ParityErrorDate));
+        assert_eq!(stats.since_good_frame_ms, Some(1200));
+        stats.edge(u32::MAX);
+        assert_eq!(stats.since_good_frame_ms, Some(u32::MAX));
+        assert_eq!(stats.edges, 4);
+    }
+
+    #[test]
+    fn pulses() {
+        let mut stats = ReceptionStats::default();
+        stats.pulse(Pulse::Zero);
+        stats.pulse(Pulse::One);
+        stats.pulse(Pulse::Invalid);
+        stats.pulse(Pulse::Glitch);
+        assert_eq!((stats.valid_bits, stats.invalid_pulses), (2, 1));
+    }
+
+    #[test]
+    fn quality() {
+        let mut stats = ReceptionStats::default();
+        assert_eq!(stats.quality(), 0);
+        stats.symbol(true);
+        assert_eq!(stats.quality(), 6);
+
+        // reaches but never exceeds the bounds
+        for _ in 0..1000 {
+            stats.symbol(true);
+            assert!(stats.quality() <= 100);
+        }
+        assert_eq!(stats.quality(), 100);
+        for _ in 0..1000 {
+            stats.symbol(false);
+        }
+        assert_eq!(stats.quality(), 0);
+    }
+
+    #[test]
+    fn quality_decay() {
+        let mut stats = ReceptionStats::default();
+        for _ in 0..1000 {
+            stats.symbol(true);
+        }
+        // a single bad pulse only moves the score a bit
+        stats.symbol(false);
+        assert_eq!(stats.quality(), 93);
+        // and half of the score is gone after a bit more than ten
+        for _ in 0..10 {
+            stats.symbol(false);
+        }
+        assert_eq!(stats.quality(), 49);
+        // glitches are not accounted for
+        stats.pulse(Pulse::Glitch);
+        assert_eq!(stats.quality(), 49);
+        // one bad pulse in ten settles at about 90
+        for _ in 0..100 {
+            for second in 0..10 {
+                stats.symbol(second != 0);
+            }
+        }
+        assert!((85..=95).contains(&stats.quality()), "{}", stats.quality());
+    }
+}
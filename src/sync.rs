@@ -0,0 +1,324 @@
+use chrono::{Duration, NaiveDateTime, Timelike};
+
+/// Maximum offset between RTC and reference which is not corrected
+const TOLERANCE_MS: i64 = 50;
+/// Maximum offset not corrected for a reference without second marker
+const COARSE_TOLERANCE_MS: i64 = 500;
+
+/// RTC as far as used by the synchronisation
+pub trait Clock {
+    type Error;
+
+    /// Date and time of the current second
+    fn datetime(&mut self) -> Result<NaiveDateTime, Self::Error>;
+
+    /// Set the date and time, restarting the current second
+    fn set_datetime(&mut self, time: &NaiveDateTime) -> Result<(), Self::Error>;
+
+    /// Milliseconds elapsed since the start of the current second
+    ///
+    /// Has to latch the calendar so that a subsequent read of the date and
+    /// time returns the matching second.
+    fn subsecond_ms(&mut self) -> i64;
+
+    /// Shift the RTC by `offset_ms`, which has to be within one second
+    fn shift_ms(&mut self, offset_ms: i64);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SyncResult {
+    /// the RTC already agreed with the reference, nothing was written
+    InSync { offset_ms: i64 },
+    /// the RTC has been set to the reference time
+    Updated { offset_ms: i64 },
+}
+
+impl SyncResult {
+    /// Offset of the RTC against the reference before the synchronisation
+    pub fn offset_ms(&self) -> i64 {
+        match *self {
+            SyncResult::InSync { offset_ms } => offset_ms,
+            SyncResult::Updated { offset_ms } => offset_ms,
+        }
+    }
+
+    /// Offset of the RTC against the reference after the synchronisation
+    pub fn remaining_offset_ms(&self) -> i64 {
+        match *self {
+            SyncResult::InSync { offset_ms } => offset_ms,
+            SyncResult::Updated { .. } => 0,
+        }
+    }
+}
+
+/// Synchronisation of the RTC to a reference time signal
+///
+/// The RTC can not count a leap second. Instead it is held back by a second
+/// from the last second of the minute on, so the next minute starts in time.
+pub struct RtcSync {
+    last_sync: Option<NaiveDateTime>,
+    /// last second of a minute followed by a leap second
+    leap_second: Option<NaiveDateTime>,
+}
+
+impl Default for RtcSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RtcSync {
+    pub fn new() -> Self {
+        Self {
+            last_sync: None,
+            leap_second: None,
+        }
+    }
+
+    /// A leap second follows the minute of `time`
+    pub fn announce_leap_second(&mut self, time: NaiveDateTime) {
+        self.leap_second = time
+            .with_second(59)
+            .and_then(|time| time.with_nanosecond(0));
+    }
+
+    /// Synchronise the RTC to `time`, the second started `delay_ms` ago
+    ///
+    /// The delay since the edge marking the start of the second has to be
+    /// less than a second. Setting the time restarts the RTC prescalers, which
+    /// aligns the subseconds to the reference once shifted by the delay.
+    pub fn second_marker<R: Clock>(
+        &mut self,
+        rtc: &mut R,
+        time: NaiveDateTime,
+        delay_ms: i64,
+    ) -> Result<SyncResult, R::Error> {
+        self.marker(rtc, time, delay_ms, TOLERANCE_MS)
+    }
+
+    /// Synchronise the RTC to `time`, which started about `delay_ms` ago
+    ///
+    /// For references without a precise second marker, like GPS sentences
+    /// without the PPS. The RTC is only set when off by half a second, so the
+    /// varying delay of the reference does not rewrite it over and over.
+    pub fn coarse_marker<R: Clock>(
+        &mut self,
+        rtc: &mut R,
+        time: NaiveDateTime,
+        delay_ms: i64,
+    ) -> Result<SyncResult, R::Error> {
+        self.marker(rtc, time, delay_ms, COARSE_TOLERANCE_MS)
+    }
+
+    fn marker<R: Clock>(
+        &mut self,
+        rtc: &mut R,
+        time: NaiveDateTime,
+        delay_ms: i64,
+        tolerance_ms: i64,
+    ) -> Result<SyncResult, R::Error> {
+        let hold = self.leap_second == Some(time);
+        let rtc_time = if time.nanosecond() >= 1_000_000_000 {
+            // the leap second itself, during which the RTC is held
+            time.with_nanosecond(time.nanosecond() - 1_000_000_000)
+                .unwrap_or(time)
+        } else if hold {
+            time - Duration::seconds(1)
+        } else {
+            time
+        };
+
+        let subseconds = rtc.subsecond_ms();
+        let offset_ms = (rtc.datetime()? - rtc_time).num_milliseconds() + subseconds - delay_ms;
+
+        let result = if hold {
+            set(rtc, rtc_time, delay_ms)?;
+            SyncResult::Updated {
+                offset_ms: offset_ms - 1000,
+            }
+        } else if offset_ms.abs() <= tolerance_ms {
+            SyncResult::InSync { offset_ms }
+        } else {
+            set(rtc, rtc_time, delay_ms)?;
+            SyncResult::Updated { offset_ms }
+        };
+
+        self.last_sync = Some(time);
+        Ok(result)
+    }
+
+    /// Align the subseconds of the RTC to a second marker `delay_ms` ago
+    ///
+    /// Used while the second is known but not the time, so only the phase
+    /// within the second is corrected. Returns the offset of the RTC.
+    pub fn phase_marker<R: Clock>(&mut self, rtc: &mut R, delay_ms: i64) -> i64 {
+        let subseconds = (rtc.subsecond_ms() - delay_ms).rem_euclid(1000);
+        let offset_ms = if subseconds < 500 {
+            subseconds
+        } else {
+            subseconds - 1000
+        };
+
+        if offset_ms.abs() > TOLERANCE_MS {
+            rtc.shift_ms(-offset_ms);
+        }
+        offset_ms
+    }
+
+    /// Reference time of the last successful synchronisation
+    pub fn last_sync(&self) -> Option<NaiveDateTime> {
+        self.last_sync
+    }
+}
+
+/// Set the RTC to `time`, which started `delay_ms` ago
+fn set<R: Clock>(rtc: &mut R, time: NaiveDateTime, delay_ms: i64) -> Result<(), R::Error> {
+    rtc.set_datetime(&time)?;
+    if delay_ms != 0 {
+        rtc.shift_ms(delay_ms);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// RTC keeping the time it was set to
+    pub struct Rtc {
+        pub time: NaiveDateTime,
+        pub subsecond_ms: i64,
+        /// number of times the time has been set
+        pub writes: u32,
+    }
+
+    impl Rtc {
+        pub fn new(time: NaiveDateTime) -> Self {
+            Self {
+                time,
+                subsecond_ms: 0,
+                writes: 0,
+            }
+        }
+    }
+
+    impl Clock for Rtc {
+        type Error = ();
+
+        fn datetime(&mut self) -> Result<NaiveDateTime, ()> {
+            Ok(self.time)
+        }
+
+        fn set_datetime(&mut self, time: &NaiveDateTime) -> Result<(), ()> {
+            self.time = *time;
+            self.subsecond_ms = 0;
+            self.writes += 1;
+            Ok(())
+        }
+
+        fn subsecond_ms(&mut self) -> i64 {
+            self.subsecond_ms
+        }
+
+        fn shift_ms(&mut self, offset_ms: i64) {
+            self.subsecond_ms += offset_ms;
+        }
+    }
+
+    fn time(second: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd(2016, 12, 31).and_hms(23, 59, 0) + Duration::seconds(second)
+    }
+
+    #[test]
+    fn second_marker() {
+        let mut sync = RtcSync::new();
+        let mut rtc = Rtc::new(time(10));
+        rtc.subsecond_ms = 30;
+        assert_eq!(
+            sync.second_marker(&mut rtc, time(10), 0),
+            Ok(SyncResult::InSync { offset_ms: 30 })
+        );
+        assert_eq!(rtc.writes, 0);
+
+        // the second started 20ms ago
+        rtc.subsecond_ms = 0;
+        assert_eq!(
+            sync.second_marker(&mut rtc, time(8), 20),
+            Ok(SyncResult::Updated { offset_ms: 1980 })
+        );
+        assert_eq!((rtc.time, rtc.subsecond_ms), (time(8), 20));
+        assert_eq!(sync.last_sync(), Some(time(8)));
+    }
+
+    #[test]
+    fn coarse_marker() {
+        let mut sync = RtcSync::new();
+        let mut rtc = Rtc::new(time(10));
+        rtc.subsecond_ms = 300;
+        for &delay_ms in [150, 250, 450].iter() {
+            assert_eq!(
+                sync.coarse_marker(&mut rtc, time(10), delay_ms),
+                Ok(SyncResult::InSync {
+                    offset_ms: 300 - delay_ms
+                })
+            );
+        }
+        assert_eq!(rtc.writes, 0);
+
+        // a second off is corrected, with the subseconds from the delay
+        assert_eq!(
+            sync.coarse_marker(&mut rtc, time(11), 200),
+            Ok(SyncResult::Updated { offset_ms: -900 })
+        );
+        assert_eq!((rtc.time, rtc.subsecond_ms), (time(11), 200));
+    }
+
+    #[test]
+    fn leap_second_hold() {
+        let mut sync = RtcSync::new();
+        sync.announce_leap_second(time(30));
+        let mut rtc = Rtc::new(time(58));
+        assert_eq!(
+            sync.second_marker(&mut rtc, time(58), 0),
+            Ok(SyncResult::InSync { offset_ms: 0 })
+        );
+
+        // the RTC is held back a second from the last regular second on
+        rtc.time = time(59);
+        assert_eq!(
+            sync.second_marker(&mut rtc, time(59), 0),
+            Ok(SyncResult::Updated { offset_ms: 0 })
+        );
+        assert_eq!(rtc.time, time(58));
+
+        // so it shows 23:59:59 during the leap second
+        rtc.time = time(59);
+        let leap_second = NaiveDate::from_ymd(2016, 12, 31).and_hms_milli(23, 59, 59, 1000);
+        assert_eq!(
+            sync.second_marker(&mut rtc, leap_second, 0),
+            Ok(SyncResult::InSync { offset_ms: 0 })
+        );
+
+        // and the next minute starts in time
+        rtc.time = time(60);
+        assert_eq!(
+            sync.second_marker(&mut rtc, time(60), 0),
+            Ok(SyncResult::InSync { offset_ms: 0 })
+        );
+        assert_eq!(rtc.writes, 1);
+    }
+
+    #[test]
+    fn phase_marker() {
+        let mut sync = RtcSync::new();
+        let mut rtc = Rtc::new(time(0));
+        rtc.subsecond_ms = 980;
+        assert_eq!(sync.phase_marker(&mut rtc, 0), -20);
+        assert_eq!(rtc.subsecond_ms, 980);
+
+        rtc.subsecond_ms = 300;
+        assert_eq!(sync.phase_marker(&mut rtc, 100), 200);
+        assert_eq!(rtc.subsecond_ms, 100);
+    }
+}
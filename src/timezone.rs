@@ -0,0 +1,456 @@
+//! Conversion from UTC to local time by POSIX TZ rules
+//!
+//! A time zone is given like the `TZ` environment variable, e.g.
+//! `CET-1CEST,M3.5.0,M10.5.0/3` for central Europe. Offsets are west of UTC
+//! as in POSIX, daylight saving time has to come with its rules, which have
+//! to be of the `Mm.w.d[/time]` form.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Syntax,
+    /// valid but not implemented, like DST without rules or Julian days
+    Unsupported,
+}
+
+/// Day and local time of a change between standard and daylight saving time
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub month: u32,
+    /// week of the month from 1 to 5, where 5 is the last one
+    pub week: u32,
+    pub weekday: Weekday,
+    /// local time of the change, may be outside of the day
+    pub time_s: i32,
+}
+
+impl Rule {
+    /// Local time of the change in `year`
+    pub fn local(&self, year: i32) -> Option<NaiveDateTime> {
+        let first = NaiveDate::from_ymd_opt(year, self.month, 1)?;
+        let first_weekday = first.weekday().num_days_from_sunday();
+        let day =
+            1 + (7 + self.weekday.num_days_from_sunday() - first_weekday) % 7 + 7 * (self.week - 1);
+        let date = NaiveDate::from_ymd_opt(year, self.month, day)
+            .or_else(|| NaiveDate::from_ymd_opt(year, self.month, day - 7))?;
+        Some(date.and_hms(0, 0, 0) + Duration::seconds(self.time_s.into()))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dst {
+    /// offset east of UTC
+    pub offset_s: i32,
+    pub start: Rule,
+    pub end: Rule,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeZone {
+    /// offset of the standard time east of UTC
+    pub offset_s: i32,
+    pub dst: Option<Dst>,
+}
+
+impl TimeZone {
+    pub const UTC: TimeZone = TimeZone {
+        offset_s: 0,
+        dst: None,
+    };
+
+    /// Parse a POSIX TZ string
+    pub fn parse(tz: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            rest: tz.as_bytes(),
+        };
+
+        parser.name()?;
+        let offset_s = -parser.time()?;
+        if parser.rest.is_empty() {
+            return Ok(TimeZone {
+                offset_s,
+                dst: None,
+            });
+        }
+
+        parser.name()?;
+        let dst_offset_s = match parser.rest.first() {
+            Some(b',') | None => offset_s + 3600,
+            Some(_) => -parser.time()?,
+        };
+        if !parser.eat(b',') {
+            return Err(Error::Unsupported);
+        }
+        let start = parser.rule()?;
+        if !parser.eat(b',') {
+            return Err(Error::Syntax);
+        }
+        let end = parser.rule()?;
+        if !parser.rest.is_empty() {
+            return Err(Error::Syntax);
+        }
+
+        Ok(TimeZone {
+            offset_s,
+            dst: Some(Dst {
+                offset_s: dst_offset_s,
+                start,
+                end,
+            }),
+        })
+    }
+
+    /// Whether daylight saving time is in effect at `utc`
+    pub fn is_dst(&self, utc: NaiveDateTime) -> bool {
+        let dst = match self.dst {
+            Some(dst) => dst,
+            None => return false,
+        };
+
+        // the start is given in standard time, the end in daylight saving time
+        let year = utc.year();
+        let start = dst
+            .start
+            .local(year)
+            .map(|start| start - seconds(self.offset_s));
+        let end = dst.end.local(year).map(|end| end - seconds(dst.offset_s));
+        match (start, end) {
+            (Some(start), Some(end)) if start < end => start <= utc && utc < end,
+            // southern hemisphere
+            (Some(start), Some(end)) => utc < end || start <= utc,
+            _ => false,
+        }
+    }
+
+    /// Offset of the local time to UTC at `utc`
+    pub fn offset(&self, utc: NaiveDateTime) -> Duration {
+        match self.dst {
+            Some(dst) if self.is_dst(utc) => seconds(dst.offset_s),
+            _ => seconds(self.offset_s),
+        }
+    }
+
+    pub fn to_local(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        utc + self.offset(utc)
+    }
+}
+
+fn seconds(seconds: i32) -> Duration {
+    Duration::seconds(seconds.into())
+}
+
+struct Parser<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    fn eat(&mut self, byte: u8) -> bool {
+        match self.rest.split_first() {
+            Some((&first, rest)) if first == byte => {
+                self.rest = rest;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Skip a name of at least three letters or any quoted in `<>`
+    fn name(&mut self) -> Result<(), Error> {
+        let len = if self.eat(b'<') {
+            let len = self
+                .rest
+                .iter()
+                .position(|&byte| byte == b'>')
+                .ok_or(Error::Syntax)?;
+            self.rest = &self.rest[len + 1..];
+            len
+        } else {
+            let len = self
+                .rest
+                .iter()
+                .take_while(|byte| byte.is_ascii_alphabetic())
+                .count();
+            self.rest = &self.rest[len..];
+            len
+        };
+
+        if len < 3 {
+            return Err(Error::Syntax);
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<i32, Error> {
+        let len = self
+            .rest
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        if len == 0 || len > 3 {
+            return Err(Error::Syntax);
+        }
+
+        let number = self.rest[..len]
+            .iter()
+            .fold(0, |number, digit| number * 10 + (digit - b'0') as i32);
+        self.rest = &self.rest[len..];
+        Ok(number)
+    }
+
+    /// Time of the form `[+-]hh[:mm[:ss]]` in seconds
+    fn time(&mut self) -> Result<i32, Error> {
+        let sign = if self.eat(b'-') {
+            -1
+        } else {
+            self.eat(b'+');
+            1
+        };
+
+        let mut seconds = self.number()? * 3600;
+        for &scale in [60, 1].iter() {
+            if !self.eat(b':') {
+                break;
+            }
+            match self.number()? {
+                number if number < 60 => seconds += number * scale,
+                _ => return Err(Error::Syntax),
+            }
+        }
+        Ok(sign * seconds)
+    }
+
+    /// Rule of the form `Mm.w.d[/time]`
+    fn rule(&mut self) -> Result<Rule, Error> {
+        if !self.eat(b'M') {
+            return Err(Error::Unsupported);
+        }
+
+        let month = self.number()?;
+        let week = if self.eat(b'.') {
+            self.number()?
+        } else {
+            return Err(Error::Syntax);
+        };
+        let weekday = if self.eat(b'.') {
+            self.number()?
+        } else {
+            return Err(Error::Syntax);
+        };
+        let time_s = if self.eat(b'/') {
+            self.time()?
+        } else {
+            2 * 3600
+        };
+
+        let weekday = match weekday {
+            0 => Weekday::Sun,
+            1 => Weekday::Mon,
+            2 => Weekday::Tue,
+            3 => Weekday::Wed,
+            4 => Weekday::Thu,
+            5 => Weekday::Fri,
+            6 => Weekday::Sat,
+            _ => return Err(Error::Syntax),
+        };
+        if !(1..=12).contains(&month) || !(1..=5).contains(&week) {
+            return Err(Error::Syntax);
+        }
+
+        Ok(Rule {
+            month: month as u32,
+            week: week as u32,
+            weekday,
+            time_s,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CET: &str = "CET-1CEST,M3.5.0,M10.5.0/3";
+    const US_EASTERN: &str = "EST5EDT,M3.2.0,M11.1.0";
+    const SYDNEY: &str = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2024, month, day).and_hms(hour, minute, second)
+    }
+
+    fn rule(month: u32, week: u32, weekday: Weekday, time_s: i32) -> Rule {
+        Rule {
+            month,
+            week,
+            weekday,
+            time_s,
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            TimeZone::parse(CET),
+            Ok(TimeZone {
+                offset_s: 3600,
+                dst: Some(Dst {
+                    offset_s: 7200,
+                    start: rule(3, 5, Weekday::Sun, 2 * 3600),
+                    end: rule(10, 5, Weekday::Sun, 3 * 3600),
+                }),
+            })
+        );
+        let us_eastern = TimeZone::parse(US_EASTERN).unwrap();
+        assert_eq!(us_eastern.offset_s, -5 * 3600);
+        assert_eq!(us_eastern.dst.map(|dst| dst.offset_s), Some(-4 * 3600));
+
+        assert_eq!(TimeZone::parse("UTC0"), Ok(TimeZone::UTC));
+        assert_eq!(
+            TimeZone::parse("<+0330>-3:30"),
+            Ok(TimeZone {
+                offset_s: 3 * 3600 + 1800,
+                dst: None,
+            })
+        );
+        // explicit DST offset and a change at 01:00 local time
+        let tz = TimeZone::parse("IST-1GMT0,M10.5.0,M3.5.0/1").unwrap();
+        assert_eq!(
+            tz.dst.map(|dst| (dst.offset_s, dst.end.time_s)),
+            Some((0, 3600))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for &tz in [
+            "",
+            "CET",
+            "CE-1",
+            "CET-1:60",
+            "CET-1CEST,M3.5.0",
+            "CET-1CEST,M3.5.0,M10.5.0/3x",
+            "CET-1CEST,M13.5.0,M10.5.0",
+            "CET-1CEST,M3.6.0,M10.5.0",
+            "CET-1CEST,M3.5.7,M10.5.0",
+            "CET-1CEST,M3.5,M10.5.0",
+            "<CET-1",
+        ]
+        .iter()
+        {
+            assert_eq!(TimeZone::parse(tz), Err(Error::Syntax), "{}", tz);
+        }
+        for &tz in ["CET-1CEST", "CET-1CEST,J60,J300", "CET-1CEST,60,300"].iter() {
+            assert_eq!(TimeZone::parse(tz), Err(Error::Unsupported), "{}", tz);
+        }
+    }
+
+    #[test]
+    fn rule_local() {
+        let date = |month, day| NaiveDate::from_ymd(2024, month, day);
+        let local = |rule: Rule| rule.local(2024).unwrap();
+        assert_eq!(
+            local(rule(3, 5, Weekday::Sun, 7200)),
+            date(3, 31).and_hms(2, 0, 0)
+        );
+        assert_eq!(
+            local(rule(3, 2, Weekday::Sun, 7200)),
+            date(3, 10).and_hms(2, 0, 0)
+        );
+        assert_eq!(
+            local(rule(11, 1, Weekday::Sun, 7200)),
+            date(11, 3).and_hms(2, 0, 0)
+        );
+        // March starts on a Friday
+        assert_eq!(
+            local(rule(3, 1, Weekday::Fri, 0)),
+            date(3, 1).and_hms(0, 0, 0)
+        );
+        assert_eq!(
+            local(rule(3, 1, Weekday::Thu, 0)),
+            date(3, 7).and_hms(0, 0, 0)
+        );
+
+        // week 5 is the last one, whether the month has four or five
+        assert_eq!(
+            local(rule(2, 5, Weekday::Sun, 0)),
+            date(2, 25).and_hms(0, 0, 0)
+        );
+        assert_eq!(
+            local(rule(2, 5, Weekday::Thu, 0)),
+            date(2, 29).and_hms(0, 0, 0)
+        );
+        assert_eq!(
+            local(rule(2, 4, Weekday::Thu, 0)),
+            date(2, 22).and_hms(0, 0, 0)
+        );
+
+        // times outside of the day move to the neighbouring one
+        assert_eq!(
+            local(rule(3, 5, Weekday::Sun, -3600)),
+            date(3, 30).and_hms(23, 0, 0)
+        );
+        assert_eq!(
+            local(rule(3, 5, Weekday::Sun, 25 * 3600)),
+            date(4, 1).and_hms(1, 0, 0)
+        );
+        let tz = TimeZone::parse("XXX-1YYY,M3.5.0/-1,M10.5.0/25").unwrap();
+        assert_eq!(
+            tz.dst.map(|dst| (dst.start.time_s, dst.end.time_s)),
+            Some((-3600, 25 * 3600))
+        );
+    }
+
+    /// Check DST ends just before `change` in UTC if `dst`, or starts at it
+    fn switch(tz: &TimeZone, change: NaiveDateTime, dst: bool) {
+        let before = change - Duration::seconds(1);
+        assert_eq!(tz.is_dst(before), dst, "{}", before);
+        assert_eq!(tz.is_dst(change), !dst, "{}", change);
+    }
+
+    #[test]
+    fn central_europe() {
+        let tz = TimeZone::parse(CET).unwrap();
+        switch(&tz, utc(3, 31, 1, 0, 0), false);
+        switch(&tz, utc(10, 27, 1, 0, 0), true);
+        assert!(!tz.is_dst(utc(1, 15, 12, 0, 0)));
+        assert!(tz.is_dst(utc(7, 15, 12, 0, 0)));
+
+        // 01:59:59 CET is followed by 03:00 CEST, 02:59:59 CEST by 02:00 CET
+        assert_eq!(tz.to_local(utc(3, 31, 0, 59, 59)), utc(3, 31, 1, 59, 59));
+        assert_eq!(tz.to_local(utc(3, 31, 1, 0, 0)), utc(3, 31, 3, 0, 0));
+        assert_eq!(tz.to_local(utc(10, 27, 0, 59, 59)), utc(10, 27, 2, 59, 59));
+        assert_eq!(tz.to_local(utc(10, 27, 1, 0, 0)), utc(10, 27, 2, 0, 0));
+    }
+
+    #[test]
+    fn us_eastern() {
+        let tz = TimeZone::parse(US_EASTERN).unwrap();
+        switch(&tz, utc(3, 10, 7, 0, 0), false);
+        switch(&tz, utc(11, 3, 6, 0, 0), true);
+        assert_eq!(tz.offset(utc(7, 4, 12, 0, 0)), Duration::hours(-4));
+        assert_eq!(tz.offset(utc(12, 24, 12, 0, 0)), Duration::hours(-5));
+    }
+
+    #[test]
+    fn southern_hemisphere() {
+        let tz = TimeZone::parse(SYDNEY).unwrap();
+        // 03:00 AEDT on 7 April and 02:00 AEST on 6 October
+        switch(&tz, utc(4, 6, 16, 0, 0), true);
+        switch(&tz, utc(10, 5, 16, 0, 0), false);
+        assert!(tz.is_dst(utc(1, 1, 0, 0, 0)));
+        assert!(!tz.is_dst(utc(7, 1, 0, 0, 0)));
+        assert!(tz.is_dst(utc(12, 31, 23, 59, 59)));
+        assert_eq!(
+            tz.to_local(utc(12, 31, 13, 0, 0)),
+            NaiveDate::from_ymd(2025, 1, 1).and_hms(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn without_dst() {
+        let tz = TimeZone::parse("JST-9").unwrap();
+        assert!(!tz.is_dst(utc(7, 1, 0, 0, 0)));
+        assert_eq!(tz.to_local(utc(7, 1, 0, 0, 0)), utc(7, 1, 9, 0, 0));
+        assert_eq!(TimeZone::UTC.offset(utc(7, 1, 0, 0, 0)), Duration::zero());
+    }
+}
@@ -0,0 +1,270 @@
+//! Recorded edge traces of a time signal and their replay through the decoder
+//!
+//! A trace is a text with one entry per line, timestamps in milliseconds:
+//!
+//! ```text
+//! # comment
+//! 1000 f
+//! 1100 r
+//! 2000 f = 2021-03-28T01:59:01
+//! ```
+//!
+//! `f` is a falling edge starting a pulse, `r` a rising edge ending it. The
+//! polarity is the one seen by the decoder, so inverting receivers have to be
+//! recorded inverted. An edge may carry the time in UTC the decoder is
+//! expected to report for the second starting with it. The traces replayed
+//! by the tests are kept in `traces/`.
+//!
+//! The traces there so far are synthetic, produced by the generator with
+//! its simulated disturbances. They keep the decoder from regressing, but
+//! only a capture of a real receiver shows its actual pulse shapes and
+//! noise. Captures go into `traces/` in the same format.
+
+use crate::signal::TimeSignal;
+use chrono::NaiveDateTime;
+use core::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TraceEdge {
+    pub time_ms: u32,
+    pub rising: bool,
+    /// time expected for the second starting with this edge
+    pub expected: Option<NaiveDateTime>,
+}
+
+impl TraceEdge {
+    pub fn new(time_ms: u32, rising: bool, expected: Option<NaiveDateTime>) -> Self {
+        Self {
+            time_ms,
+            rising,
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for TraceEdge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let polarity = if self.rising { 'r' } else { 'f' };
+        write!(f, "{} {}", self.time_ms, polarity)?;
+        if let Some(expected) = self.expected {
+            write!(f, " = {:?}", expected)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceError {
+    /// line could not be parsed
+    Syntax,
+    /// timestamp before the one of the previous edge
+    Timestamp,
+}
+
+/// Parse a single line of a trace, `None` for empty lines and comments
+pub fn parse_line(line: &str) -> Result<Option<TraceEdge>, TraceError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (edge, expected) = match line.find('=') {
+        Some(pos) => (&line[..pos], Some(line[pos + 1..].trim())),
+        None => (line, None),
+    };
+
+    let mut fields = edge.split_whitespace();
+    let time_ms = fields
+        .next()
+        .ok_or(TraceError::Syntax)?
+        .parse()
+        .map_err(|_| TraceError::Syntax)?;
+    let rising = match fields.next() {
+        Some("r") => true,
+        Some("f") => false,
+        _ => return Err(TraceError::Syntax),
+    };
+    if fields.next().is_some() {
+        return Err(TraceError::Syntax);
+    }
+    let expected = match expected {
+        Some(expected) => Some(expected.parse().map_err(|_| TraceError::Syntax)?),
+        None => None,
+    };
+
+    Ok(Some(TraceEdge {
+        time_ms,
+        rising,
+        expected,
+    }))
+}
+
+/// A second reported by the decoder which did not match the trace
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub time_ms: u32,
+    pub expected: NaiveDateTime,
+    /// `None` if the decoder did not report a time at all
+    pub decoded: Option<NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub edges: u32,
+    /// seconds the decoder reported a time for
+    pub decoded: u32,
+    /// edges with an expected time
+    pub checked: u32,
+    pub mismatches: u32,
+    /// mismatches the decoder did not report a time for at all
+    pub missed: u32,
+    pub first_mismatch: Option<Mismatch>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0
+    }
+}
+
+/// Replay of edges through the decoder, checking the reported times
+pub struct Replay<D: TimeSignal> {
+    decoder: D,
+    last_ms: Option<u32>,
+    report: Report,
+}
+
+impl<D: TimeSignal> Replay<D> {
+    pub fn new(decoder: D) -> Self {
+        Self {
+            decoder,
+            last_ms: None,
+            report: Report::default(),
+        }
+    }
+
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    pub fn report(&self) -> Report {
+        self.report
+    }
+
+    /// Feed an edge to the decoder
+    ///
+    /// Returns the time reported for the second starting with the edge.
+    /// Timestamps going backwards are rejected.
+    pub fn edge(&mut self, edge: &TraceEdge) -> Result<Option<NaiveDateTime>, TraceError> {
+        let delta_ms = match self.last_ms {
+            Some(last_ms) => edge
+                .time_ms
+                .checked_sub(last_ms)
+                .ok_or(TraceError::Timestamp)?,
+            // the decoder can not make any use of the first edge anyway
+            None => 0,
+        };
+        self.last_ms = Some(edge.time_ms);
+
+        self.decoder.update(edge.rising, delta_ms);
+        self.report.edges += 1;
+
+        let decoded = if self.decoder.second_started() {
+            self.decoder.now().ok()
+        } else {
+            None
+        };
+        if decoded.is_some() {
+            self.report.decoded += 1;
+        }
+
+        if let Some(expected) = edge.expected {
+            self.report.checked += 1;
+            if decoded != Some(expected) {
+                self.report.mismatches += 1;
+                if decoded.is_none() {
+                    self.report.missed += 1;
+                }
+                if self.report.first_mismatch.is_none() {
+                    self.report.first_mismatch = Some(Mismatch {
+                        time_ms: edge.time_ms,
+                        expected,
+                        decoded,
+                    });
+                }
+            }
+        }
+
+        Ok(decoded)
+    }
+}
+
+/// Replay a whole trace through `decoder`
+///
+/// Errors come with the number of the line they occurred in.
+pub fn replay<D: TimeSignal>(trace: &str, decoder: D) -> Result<Report, (usize, TraceError)> {
+    let mut replay = Replay::new(decoder);
+    for (index, line) in trace.lines().enumerate() {
+        let edge = parse_line(line).map_err(|error| (index + 1, error))?;
+        if let Some(edge) = edge {
+            replay.edge(&edge).map_err(|error| (index + 1, error))?;
+        }
+    }
+
+    Ok(replay.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classifier::PulseClassifier;
+    use crate::dcf77::DCF77;
+
+    /// Decoder as set up by the firmware
+    fn decoder() -> DCF77 {
+        let mut decoder = DCF77::init(PulseClassifier::default(), 2);
+        decoder.set_correction(Some(2));
+        decoder
+    }
+
+    /// Synthetic trace, see `traces/`
+    #[test]
+    fn clean_trace() {
+        let report = replay(include_str!("../traces/dcf77-clean.txt"), decoder()).unwrap();
+        assert_eq!(report.checked, 5 * 59);
+        assert!(report.passed(), "{:?}", report);
+    }
+
+    /// Synthetic trace, see `traces/`
+    #[test]
+    fn noisy_trace() {
+        let report = replay(include_str!("../traces/dcf77-noisy.txt"), decoder()).unwrap();
+        assert!(report.checked > 7 * 59);
+        // dropped pulses may cost a second, but never give a wrong time
+        assert_eq!(report.mismatches, report.missed, "{:?}", report);
+        assert!(report.missed * 50 < report.checked, "{:?}", report);
+    }
+
+    #[test]
+    fn lines() {
+        let edge = TraceEdge::new(12, false, Some("2021-03-28T01:59:01".parse().unwrap()));
+        assert_eq!(parse_line("12 f = 2021-03-28T01:59:01"), Ok(Some(edge)));
+        assert_eq!(parse_line(&edge.to_string()), Ok(Some(edge)));
+        assert_eq!(
+            parse_line(" 100 r "),
+            Ok(Some(TraceEdge::new(100, true, None)))
+        );
+        assert_eq!(parse_line("# comment"), Ok(None));
+        assert_eq!(parse_line(""), Ok(None));
+        assert_eq!(parse_line("100 x"), Err(TraceError::Syntax));
+        assert_eq!(parse_line("100 r r"), Err(TraceError::Syntax));
+        assert_eq!(parse_line("100 r = noon"), Err(TraceError::Syntax));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let trace = "1000 f\n# comment\n500 r\n";
+        assert_eq!(replay(trace, decoder()), Err((3, TraceError::Timestamp)));
+        assert_eq!(replay("1000 x", decoder()), Err((1, TraceError::Syntax)));
+    }
+}
@@ -0,0 +1,239 @@
+//! WWVB, transmitted from Fort Collins in the US on 60kHz
+//!
+//! The carrier is reduced at the start of every second for 200ms for a zero,
+//! 500ms for a one and 800ms for a marker. Two consecutive markers start a
+//! minute. The time is UTC and refers to the minute it is transmitted in, the
+//! frame carries the US daylight saving time status as `cest`.
+
+use crate::dcf77::{DCF77Frame, Error};
+use crate::generator::{Encoder, Flags};
+use crate::signal::{
+    insert_weighted, marker_bits, matches, weighted, Decoder, Field, Pulses, Station, Symbol,
+};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+
+const TOLERANCE_MS: u32 = 60;
+/// Seconds transmitting a marker
+const MARKERS: [usize; 7] = [0, 9, 19, 29, 39, 49, 59];
+
+const MINUTE: Field = (1, &[40, 20, 10, 0, 8, 4, 2, 1]);
+const HOUR: Field = (12, &[20, 10, 0, 8, 4, 2, 1]);
+const DAY_OF_YEAR: Field = (22, &[200, 100, 0, 80, 40, 20, 10, 0, 8, 4, 2, 1]);
+const YEAR: Field = (45, &[80, 40, 20, 10, 0, 8, 4, 2, 1]);
+
+pub struct Wwvb;
+
+pub type WWVB = Decoder<Wwvb>;
+
+impl Station for Wwvb {
+    type Symbol = Symbol;
+
+    fn symbol(pulses: &[(u32, u32)]) -> Option<Symbol> {
+        match *pulses {
+            [(0, width)] if matches(width, 200, TOLERANCE_MS) => Some(Symbol::Zero),
+            [(0, width)] if matches(width, 500, TOLERANCE_MS) => Some(Symbol::One),
+            [(0, width)] if matches(width, 800, TOLERANCE_MS) => Some(Symbol::Marker),
+            _ => None,
+        }
+    }
+
+    fn minute_start(previous: Option<Symbol>, symbol: Option<Symbol>) -> bool {
+        previous == Some(Symbol::Marker) && symbol == Some(Symbol::Marker)
+    }
+
+    fn decode(
+        symbols: &[Option<Symbol>],
+        _previous: Option<&DCF77Frame>,
+    ) -> Result<DCF77Frame, Error> {
+        let bits = marker_bits(symbols, &MARKERS)?;
+        let field = |field| weighted(bits, field);
+        // without a weekday transmitted the century can't be inferred
+        let year = 2000 + field(YEAR)? as i32;
+        let leap_year = (bits >> 55) & 1 == 1;
+        if NaiveDate::from_ymd_opt(year, 2, 29).is_some() != leap_year {
+            return Err(Error::InvalidDate);
+        }
+        let date = NaiveDate::from_yo_opt(year, field(DAY_OF_YEAR)?).ok_or(Error::InvalidDate)?;
+        // transmitted is the start of the minute just completed
+        let next = date
+            .and_hms_opt(field(HOUR)?, field(MINUTE)?, 0)
+            .ok_or(Error::InvalidTime)?
+            + Duration::minutes(1);
+        let summer_time = (bits >> 58) & 1 == 1;
+
+        Ok(DCF77Frame {
+            call_bit: false,
+            // a change is announced for the end of the day
+            dst_announcement: (bits >> 57) & 1 != (bits >> 58) & 1,
+            cest: summer_time,
+            cet: !summer_time,
+            leap_second_announcement: (bits >> 56) & 1 == 1,
+            minute: next.minute(),
+            hour: next.hour(),
+            day: next.day(),
+            weekday: next.weekday(),
+            month: next.month(),
+            year: next.year(),
+        })
+    }
+
+    fn utc_offset(_frame: &DCF77Frame) -> Duration {
+        Duration::zero()
+    }
+}
+
+/// Bits of the minute starting at `time`, markers excluded
+pub fn encode(time: NaiveDateTime, flags: &Flags) -> u64 {
+    let mut bits = 0u64;
+    insert_weighted(&mut bits, MINUTE, time.minute());
+    insert_weighted(&mut bits, HOUR, time.hour());
+    insert_weighted(&mut bits, DAY_OF_YEAR, time.ordinal());
+    insert_weighted(&mut bits, YEAR, (time.year() % 100) as u32);
+
+    let leap_year = NaiveDate::from_ymd_opt(time.year(), 2, 29).is_some();
+    bits |= (leap_year as u64) << 55;
+    bits |= (flags.leap_second_announcement as u64) << 56;
+    // a change is announced for the end of the day
+    bits |= ((flags.summer_time ^ flags.dst_announcement) as u64) << 57;
+    bits |= (flags.summer_time as u64) << 58;
+    bits
+}
+
+impl Encoder for Wwvb {
+    fn encode_second(minute: NaiveDateTime, flags: &Flags, second: u32) -> Option<Pulses> {
+        if second >= 60 {
+            return None;
+        }
+
+        let width_ms = if MARKERS.contains(&(second as usize)) {
+            800
+        } else if (encode(minute, flags) >> second) & 1 == 1 {
+            500
+        } else {
+            200
+        };
+        let mut pulses = Pulses::new();
+        pulses.push((0, width_ms)).ok();
+        Some(pulses)
+    }
+
+    fn flip(pulses: &mut Pulses) {
+        for (_, width_ms) in pulses.iter_mut() {
+            *width_ms = match *width_ms {
+                200 => 500,
+                500 => 200,
+                width_ms => width_ms,
+            };
+        }
+    }
+
+    fn utc_offset(_flags: &Flags) -> Duration {
+        Duration::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::{check, run, MILD};
+    use crate::generator::{Generator, Impairments};
+    use crate::trace::Replay;
+
+    fn time(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2024, 2, 28).and_hms(hour, minute, 0)
+    }
+
+    /// Symbols of a minute with `bits`
+    fn symbols(bits: u64) -> std::vec::Vec<Option<Symbol>> {
+        (0..60)
+            .map(|second| match (bits >> second) & 1 {
+                _ if MARKERS.contains(&second) => Some(Symbol::Marker),
+                0 => Some(Symbol::Zero),
+                _ => Some(Symbol::One),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let flags = Flags {
+            summer_time: true,
+            dst_announcement: true,
+            ..Flags::default()
+        };
+        let generator = Generator::<Wwvb>::new(time(23, 55), flags, Impairments::default(), 1);
+        // every second from the end of the third minute marker on
+        check(run(generator, WWVB::init(2), 10), 7 * 60 - 1);
+
+        let generator = Generator::<Wwvb>::new(time(23, 55), flags, Impairments::default(), 1);
+        let mut replay = Replay::new(WWVB::init(2));
+        // the third minute marker is decoded once the following second starts
+        for edge in generator.take_while(|edge| edge.time_ms <= 3 * 60_000 + 1000) {
+            replay.edge(&edge).unwrap();
+        }
+        let frame = replay.decoder().frame().unwrap();
+        assert_eq!(frame.datetime(0), Ok(time(23, 58)));
+        assert!(frame.cest && !frame.cet && frame.dst_announcement);
+    }
+
+    #[test]
+    fn noisy() {
+        for seed in 1..5 {
+            let generator = Generator::<Wwvb>::new(time(23, 55), Flags::default(), MILD, seed);
+            check(run(generator, WWVB::init(2), 20), 14 * 60);
+        }
+    }
+
+    #[test]
+    fn dst_bits() {
+        for &(summer_time, dst_announcement, bit_57, bit_58) in [
+            (false, false, 0, 0),
+            (false, true, 1, 0),
+            (true, true, 0, 1),
+            (true, false, 1, 1),
+        ]
+        .iter()
+        {
+            let flags = Flags {
+                summer_time,
+                dst_announcement,
+                ..Flags::default()
+            };
+            let bits = encode(time(13, 59), &flags);
+            assert_eq!(((bits >> 57) & 1, (bits >> 58) & 1), (bit_57, bit_58));
+
+            // the change is not due at the end of the hour
+            let frame = Wwvb::decode(&symbols(bits), None).unwrap();
+            assert_eq!(frame.datetime(0), Ok(time(14, 0)));
+            assert_eq!(frame.cest, summer_time);
+            assert_eq!(frame.cet, !summer_time);
+            assert_eq!(frame.dst_announcement, dst_announcement);
+        }
+    }
+
+    #[test]
+    fn frame() {
+        let bits = encode(time(13, 37), &Flags::default());
+        let frame = Wwvb::decode(&symbols(bits), None).unwrap();
+        assert_eq!(frame.datetime(0), Ok(time(13, 38)));
+        assert!(!frame.leap_second_announcement);
+
+        // 2024 is a leap year
+        assert_eq!((bits >> 55) & 1, 1);
+        assert_eq!(
+            Wwvb::decode(&symbols(bits ^ (1 << 55)), None),
+            Err(Error::InvalidDate)
+        );
+
+        let frame = Wwvb::decode(&symbols(bits | (1 << 56)), None).unwrap();
+        assert!(frame.leap_second_announcement);
+
+        // a leap second is transmitted as a marker at the end of the minute
+        let mut leap = symbols(bits);
+        leap.push(Some(Symbol::Marker));
+        assert_eq!(Wwvb::decode(&leap, None).map(|frame| frame.minute), Ok(38));
+        leap.pop();
+        leap.push(Some(Symbol::Zero));
+        assert_eq!(Wwvb::decode(&leap, None), Err(Error::ProtocolError));
+    }
+}